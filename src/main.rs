@@ -1,22 +1,87 @@
 use axum::{
-    http::StatusCode,
-    response::Json,
-    routing::post,
+    body::Bytes,
+    extract::{FromRequest, MatchedPath, OptionalFromRequest, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
     Router,
 };
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::RpcTransactionConfig,
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    rpc_request::{RpcError, RpcRequest},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::{http_sender::HttpSender, rpc_client::RpcClientConfig};
+use solana_offchain_message::{MessageFormat, OffchainMessage};
+use solana_transaction_status_client_types::{
+    EncodedConfirmedTransactionWithStatusMeta, TransactionConfirmationStatus, UiLoadedAddresses, UiTransactionEncoding,
+};
 use solana_sdk::{
+    account::Account,
+    commitment_config::CommitmentConfig,
+    derivation_path::DerivationPath,
+    epoch_info::EpochInfo,
+    hash::Hash,
     instruction::{AccountMeta, Instruction},
-    pubkey::Pubkey,
-    signer::{keypair::Keypair, Signer},
+    message::{Message, VersionedMessage},
+    transaction::VersionedTransaction,
+    native_token::LAMPORTS_PER_SOL,
+    packet::PACKET_DATA_SIZE,
+    rent::Rent,
+    pubkey,
+    pubkey::{Pubkey, MAX_SEED_LEN},
+    signer::{
+        keypair::{keypair_from_seed, keypair_from_seed_and_derivation_path, Keypair},
+        Signer,
+    },
     signature::Signature,
 };
-use solana_sdk::system_instruction;
-use spl_token;
-use spl_associated_token_account::instruction as spl_associated_token_instruction;
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+use solana_system_interface::{
+    instruction::{self as system_instruction, SystemInstruction},
+    MAX_PERMITTED_DATA_LENGTH,
+};
+use spl_associated_token_account::{get_associated_token_address, get_associated_token_address_with_program_id};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use spl_token::solana_program::program_pack::Pack;
+use spl_token::instruction::{AuthorityType, TokenInstruction};
+use spl_token_2022::extension::{transfer_fee::MAX_FEE_BASIS_POINTS, ExtensionType, StateWithExtensions};
+use spl_token::solana_program::program_error::ProgramError;
+use spl_token::solana_program::program_option::COption;
+use spl_token::state::AccountState;
+use spl_token_metadata_interface::state::Field;
+use hmac::{Hmac, Mac};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use tokio::net::TcpListener;
+use tower_http::compression::{
+    predicate::{DefaultPredicate, Predicate, SizeAbove},
+    CompressionLayer,
+};
+use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tracing::Instrument;
 use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{
+    aead::Aead,
+    XChaCha20Poly1305, XNonce,
+};
 
 
 #[derive(Serialize)]
@@ -29,6 +94,17 @@ struct SuccessResponse<T> {
 struct ErrorResponse {
     success: bool,
     error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<HashMap<String, String>>,
+    #[serde(rename = "correlationId", skip_serializing_if = "Option::is_none")]
+    correlation_id: Option<Box<str>>,
+}
+
+tokio::task_local! {
+    /// The current request's correlation id, set by the `correlation_id` middleware.
+    static CORRELATION_ID: Box<str>;
 }
 
 impl ErrorResponse {
@@ -36,10 +112,344 @@ impl ErrorResponse {
         ErrorResponse {
             success: false,
             error: msg.to_string(),
+            code: None,
+            fields: None,
+            correlation_id: CORRELATION_ID.try_with(|id| id.clone()).ok(),
+        }
+    }
+
+    /// Attaches a machine-readable error code.
+    fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+}
+
+/// Text encodings accepted for binary values such as signatures.
+#[derive(Clone, Copy)]
+enum Encoding {
+    Base58,
+    Base64,
+    Hex,
+}
+
+impl Encoding {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "base58" => Some(Encoding::Base58),
+            "base64" => Some(Encoding::Base64),
+            "hex" => Some(Encoding::Hex),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Base58 => "base58",
+            Encoding::Base64 => "base64",
+            Encoding::Hex => "hex",
+        }
+    }
+
+    fn decode(self, value: &str) -> Option<Vec<u8>> {
+        match self {
+            Encoding::Base58 => bs58::decode(value).into_vec().ok(),
+            Encoding::Base64 => general_purpose::STANDARD.decode(value).ok(),
+            Encoding::Hex => hex::decode(value).ok(),
+        }
+    }
+
+    fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Base58 => bs58::encode(bytes).into_string(),
+            Encoding::Base64 => general_purpose::STANDARD.encode(bytes),
+            Encoding::Hex => hex::encode(bytes),
+        }
+    }
+}
+
+/// Collects field-level validation failures so a handler can report all of
+/// them at once instead of stopping at the first bad field.
+#[derive(Default)]
+struct Validation {
+    summary: Option<String>,
+    fields: HashMap<String, String>,
+}
+
+impl Validation {
+    /// Records a failure for `field`. `summary` is used as the top-level error
+    /// when this turns out to be the only failure.
+    fn fail(&mut self, field: &str, detail: &str, summary: &str) {
+        if self.summary.is_none() {
+            self.summary = Some(summary.to_string());
+        }
+        self.fields.entry(field.to_string()).or_insert_with(|| detail.to_string());
+    }
+
+    /// Records a failure when a batch of `len` `noun` exceeds `limit`, naming
+    /// the env var that sets it.
+    fn batch_limit(&mut self, field: &str, len: usize, limit: BatchLimit, noun: &str) {
+        if len > limit.max {
+            self.fail(
+                field,
+                &format!("must contain at most {} {} ({})", limit.max, noun, limit.env),
+                &format!("Too many {} in one request; the limit is {} ({})", noun, limit.max, limit.env),
+            );
+        }
+    }
+
+    /// Parses a public key, recording a failure and returning the default key
+    /// when it is invalid. The result is only meaningful once `finish` is `Ok`.
+    fn pubkey(&mut self, field: &str, value: &str, summary: &str) -> Pubkey {
+        match Pubkey::from_str(value) {
+            Ok(pk) => pk,
+            Err(_) => {
+                self.fail(field, "invalid public key", summary);
+                Pubkey::default()
+            }
+        }
+    }
+
+    /// Resolves an optional `token`/`token2022` selector to its program id,
+    /// defaulting to the classic token program.
+    fn token_program(&mut self, field: &str, value: Option<&str>) -> Pubkey {
+        match value {
+            None | Some("token") => spl_token::ID,
+            Some("token2022") => spl_token_2022::ID,
+            Some(_) => {
+                self.fail(field, "must be token or token2022", "Invalid token program; must be token or token2022");
+                spl_token::ID
+            }
+        }
+    }
+
+    /// Like `pubkey`, for fields that may be omitted.
+    fn optional_pubkey(&mut self, field: &str, value: Option<&str>, summary: &str) -> Option<Pubkey> {
+        value.map(|value| self.pubkey(field, value, summary))
+    }
+
+    /// Parses a base64 ed25519 signature, recording a failure and returning the
+    /// default signature when it is malformed.
+    fn signature(&mut self, field: &str, value: &str) -> Signature {
+        match general_purpose::STANDARD.decode(value) {
+            Ok(bytes) => match Signature::try_from(bytes.as_slice()) {
+                Ok(sig) => sig,
+                Err(_) => {
+                    self.fail(field, "must be 64 bytes", "Invalid signature length");
+                    Signature::default()
+                }
+            },
+            Err(_) => {
+                self.fail(field, "must be base64", "Invalid signature format; must be base64");
+                Signature::default()
+            }
+        }
+    }
+
+    /// Parses an encoding name, recording a failure and falling back to base58.
+    fn encoding(&mut self, field: &str, value: &str) -> Encoding {
+        Encoding::parse(value).unwrap_or_else(|| {
+            self.fail(field, "must be base58, base64 or hex", "Invalid encoding; must be base58, base64 or hex");
+            Encoding::Base58
+        })
+    }
+
+    fn is_valid(&self, field: &str) -> bool {
+        !self.fields.contains_key(field)
+    }
+
+    fn finish(self) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+        let Some(summary) = self.summary else {
+            return Ok(());
+        };
+        let error = if self.fields.len() > 1 {
+            format!("{} fields failed validation", self.fields.len())
+        } else {
+            summary
+        };
+        Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                fields: Some(self.fields),
+                ..ErrorResponse::new(&error)
+            }),
+        ))
+    }
+}
+
+/// Drop-in for `axum::Json` whose rejections are `ErrorResponse` bodies rather
+/// than axum's plain text. Numbers that do not fit an unsigned integer field
+/// are reported against that field by name.
+struct Json<T>(T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+impl<T: DeserializeOwned, S: Send + Sync> FromRequest<S> for Json<T> {
+    type Rejection = (StatusCode, Json<ErrorResponse>);
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !is_json_content_type(req.headers()) {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                Json(ErrorResponse::new("Expected request with `Content-Type: application/json`")),
+            ));
+        }
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|rejection| (rejection.status(), Json(ErrorResponse::new(&rejection.body_text()))))?;
+        let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+        let value = serde_path_to_error::deserialize(&mut deserializer).map_err(json_body_error)?;
+        deserializer.end().map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new(&format!("Invalid JSON body: {}", e))),
+            )
+        })?;
+        Ok(Json(value))
+    }
+}
+
+/// Like axum's: a missing body (no `Content-Type`) extracts as `None`.
+impl<T: DeserializeOwned, S: Send + Sync> OptionalFromRequest<S> for Json<T> {
+    type Rejection = (StatusCode, Json<ErrorResponse>);
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        if !req.headers().contains_key(header::CONTENT_TYPE) {
+            return Ok(None);
+        }
+        <Self as FromRequest<S>>::from_request(req, state).await.map(Some)
+    }
+}
+
+/// `application/json` or any `application/*+json` type, parameters ignored.
+fn is_json_content_type(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers.get(header::CONTENT_TYPE).and_then(|value| value.to_str().ok()) else {
+        return false;
+    };
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence == "application/json" || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+fn json_body_error(e: serde_path_to_error::Error<serde_json::Error>) -> (StatusCode, Json<ErrorResponse>) {
+    let field = e.path().to_string();
+    let inner = e.into_inner();
+    if inner.classify() != serde_json::error::Category::Data {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(&format!("Invalid JSON body: {}", inner))),
+        );
+    }
+    if let Some(detail) = unsigned_integer_problem(&inner.to_string()) {
+        // Flattened and internally tagged structs lose the path to the field.
+        if field == "." {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new(&format!("A numeric field {}", detail))),
+            );
         }
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                fields: Some(HashMap::from([(field.clone(), detail.clone())])),
+                ..ErrorResponse::new(&format!("{} {}", field, detail))
+            }),
+        );
     }
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(ErrorResponse::new(&format!("Invalid request body at {}: {}", field, inner))),
+    )
+}
+
+/// Explains why serde rejected a number for an unsigned integer field, from
+/// its "invalid value: integer `-1`, expected u64" style of message. Integers
+/// arrive here only when negative or too large for the type; floats only when
+/// fractional or too large for any integer.
+fn unsigned_integer_problem(message: &str) -> Option<String> {
+    let (found, expected) = message.split_once(", expected ")?;
+    let max = match expected.split_whitespace().next()? {
+        "u8" => u8::MAX as u64,
+        "u16" => u16::MAX as u64,
+        "u32" => u32::MAX as u64,
+        "u64" => u64::MAX,
+        _ => return None,
+    };
+    let number: f64 = found.split('`').nth(1)?.parse().ok()?;
+    let too_large = if found.contains("integer") {
+        number > 0.0
+    } else {
+        number.fract() == 0.0 && number >= u64::MAX as f64
+    };
+    Some(if too_large {
+        format!("must be at most {}", max)
+    } else {
+        "must be a non-negative integer".to_string()
+    })
+}
+
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    #[serde(rename = "readOnly")]
+    read_only: bool,
+}
+
+#[derive(Serialize)]
+struct StatsResponse {
+    #[serde(rename = "uptimeSeconds")]
+    uptime_seconds: u64,
+    #[serde(rename = "totalRequests")]
+    total_requests: u64,
+    #[serde(rename = "clientErrors")]
+    client_errors: u64,
+    #[serde(rename = "serverErrors")]
+    server_errors: u64,
+    routes: HashMap<String, RouteStatsResponse>,
+    caches: CacheStatsResponse,
+}
+
+#[derive(Serialize)]
+struct CacheStatsResponse {
+    #[serde(rename = "mintInfo")]
+    mint_info: CacheCountersResponse,
+    #[serde(rename = "tokenAccountInfo")]
+    token_account_info: CacheCountersResponse,
+}
+
+#[derive(Serialize)]
+struct CacheCountersResponse {
+    hits: u64,
+    misses: u64,
+    entries: usize,
 }
 
+#[derive(Serialize)]
+struct RouteStatsResponse {
+    requests: u64,
+    errors: u64,
+}
+
+#[derive(Clone, Serialize)]
+struct ClusterInfoResponse {
+    slot: u64,
+    blockhash: String,
+    #[serde(rename = "lastValidBlockHeight")]
+    last_valid_block_height: u64,
+    #[serde(rename = "epochInfo")]
+    epoch_info: EpochInfo,
+}
+
+#[derive(Deserialize)]
+struct GenerateKeypairRequest {
+    /// `TEST_MODE` only: 32 bytes of hex used as the secret key seed.
+    #[serde(rename = "entropyHex")]
+    entropy_hex: Option<String>,
+}
 
 #[derive(Serialize)]
 struct KeypairResponse {
@@ -48,358 +458,6962 @@ struct KeypairResponse {
 }
 
 #[derive(Deserialize)]
-struct CreateTokenRequest {
-    #[serde(rename = "mintAuthority")]
-    mint_authority: String,
-    mint: String,
-    decimals: u8,
+struct VanityRequest {
+    /// Case-sensitive base58 pattern.
+    pattern: String,
+    /// `prefix` (default), `suffix` or `contains`.
+    #[serde(rename = "matchMode")]
+    match_mode: Option<String>,
 }
 
-#[derive(Deserialize)]
-struct MintTokenRequest {
-    mint: String,
-    destination: String,
-    authority: String,
-    amount: u64,
+#[derive(Serialize)]
+struct VanityResponse {
+    pubkey: String,
+    secret: String,
+    attempts: u64,
+    #[serde(rename = "elapsedMs")]
+    elapsed_ms: u64,
 }
 
 #[derive(Deserialize)]
-struct SignMessageRequest {
-    message: String,
+struct InspectKeypairRequest {
     secret: String,
 }
 
+#[derive(Deserialize)]
+struct KeypairPubkeyRequest {
+    secret: SecretKeyInput,
+}
+
+/// A secret key as a base58 string or as a JSON array of its 64 bytes.
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected a base58 string or an array of bytes")]
+enum SecretKeyInput {
+    Base58(String),
+    Bytes(Vec<u8>),
+}
+
+/// Deliberately carries only the public half.
 #[derive(Serialize)]
-struct SignMessageResponse {
-    signature: String,
-    public_key: String,
-    message: String,
+struct InspectKeypairResponse {
+    pubkey: String,
 }
 
 #[derive(Deserialize)]
-struct VerifyMessageRequest {
-    message: String,
+struct SignChallengeRequest {
+    challenge: String,
+    /// Leaves the ephemeral secret out when only the proof matters.
+    #[serde(default, rename = "omitSecret")]
+    omit_secret: bool,
+}
+
+#[derive(Serialize)]
+struct SignChallengeResponse {
+    pubkey: String,
+    /// Base64 signature over the UTF-8 challenge bytes.
     signature: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EncryptKeypairRequest {
+    passphrase: String,
+}
+
+/// A secret key sealed under a passphrase. Pass it back unchanged to
+/// `/keypair/decrypt`.
+#[derive(Serialize, Deserialize)]
+struct EncryptedSecret {
+    /// Scheme version; 1 is scrypt (log2 N = 15, r = 8, p = 1) feeding
+    /// XChaCha20-Poly1305.
+    version: u8,
+    /// Base64 16-byte scrypt salt.
+    salt: String,
+    /// Base64 24-byte XChaCha20 nonce.
+    nonce: String,
+    /// Base64 ciphertext of the 64 keypair bytes, tag included.
+    ciphertext: String,
+}
+
+#[derive(Serialize)]
+struct EncryptedKeypairResponse {
     pubkey: String,
+    encrypted: EncryptedSecret,
 }
 
+#[derive(Deserialize)]
+struct DecryptKeypairRequest {
+    passphrase: String,
+    encrypted: EncryptedSecret,
+}
+
+#[derive(Deserialize)]
+struct DetectKeyFormatRequest {
+    value: String,
+}
+
+/// Nothing derived from the secret beyond its length and, for a consistent
+/// keypair, the public half.
 #[derive(Serialize)]
-struct VerifyMessageResponse {
-    valid: bool,
-    message: String,
+struct DetectKeyFormatResponse {
+    /// Every encoding the value decodes under; empty when none do.
+    formats: Vec<DetectedKeyFormat>,
+}
+
+#[derive(Serialize)]
+struct DetectedKeyFormat {
+    /// `base58`, `hex` or `jsonArray`.
+    format: &'static str,
+    bytes: usize,
+    /// `keypair` (64 bytes whose halves match), `mismatchedKeypair` (64 bytes
+    /// whose halves do not), `seed` (32 bytes) or `unknown`.
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pubkey: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OffCurveRequest {
+    /// Program the address is derived under; defaults to the system program.
+    #[serde(rename = "programId")]
+    program_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct OffCurveResponse {
+    address: String,
+    #[serde(rename = "programId")]
+    program_id: String,
+    /// Hex-encoded random seed passed to the derivation.
+    seed: String,
+    bump: u8,
+}
+
+#[derive(Deserialize)]
+struct HdBatchRequest {
+    mnemonic: String,
+    #[serde(default, rename = "startIndex")]
+    start_index: u32,
+    count: u32,
+    /// Hardened derivation path with `{i}` standing in for each index;
+    /// defaults to `DEFAULT_HD_PATH_TEMPLATE`.
+    #[serde(rename = "pathTemplate")]
+    path_template: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HdBatchResponse {
+    keypairs: Vec<HdKeypair>,
+}
+
+#[derive(Serialize)]
+struct HdKeypair {
+    index: u32,
+    path: String,
     pubkey: String,
+    secret: String,
 }
 
 #[derive(Deserialize)]
-struct SendSolRequest {
-    from: String,
-    to: String,
+struct DerivationValidateRequest {
+    path: String,
+}
+
+/// `normalized` and `components` are present whenever the path parses, even if
+/// it is not usable for Solana; `reason` explains any `valid: false`.
+#[derive(Serialize)]
+struct DerivationValidateResponse {
+    valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalized: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    components: Option<Vec<DerivationComponent>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DerivationComponent {
+    index: u32,
+    hardened: bool,
+}
+
+#[derive(Deserialize)]
+struct KeypairWithCreateAccountRequest {
+    payer: String,
     lamports: u64,
+    space: u64,
+    owner: String,
+}
+
+#[derive(Serialize)]
+struct KeypairWithCreateAccountResponse {
+    pubkey: String,
+    secret: String,
+    instruction: SerializableInstruction,
 }
 
 #[derive(Deserialize)]
-struct SendTokenRequest {
-    destination: String,
+struct TokenLaunchRequest {
+    /// Funds the mint account and the recipient's ATA.
+    payer: String,
+    #[serde(rename = "mintAuthority")]
+    mint_authority: String,
+    decimals: u8,
+    /// Raw amount minted to the recipient; `0` skips the `mint_to`.
+    #[serde(rename = "initialSupply")]
+    initial_supply: u64,
+    recipient: String,
+}
+
+#[derive(Serialize)]
+struct TokenLaunchResponse {
     mint: String,
-    owner: String,
-    amount: u64,
+    /// The freshly generated mint keypair; it must co-sign the transaction.
+    #[serde(rename = "mintSecret")]
+    mint_secret: String,
+    /// The recipient's associated token account.
+    ata: String,
+    /// From the cluster when RPC is configured, otherwise the default rent schedule.
+    #[serde(rename = "rentLamports")]
+    rent_lamports: u64,
+    /// create_account, initialize_mint, create ATA (idempotent), then mint_to.
+    instructions: Vec<SerializableInstruction>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct CreateTokenRequest {
+    #[serde(rename = "mintAuthority")]
+    mint_authority: String,
+    mint: String,
+    /// Falls back to `DEFAULT_TOKEN_DECIMALS` when omitted.
+    decimals: Option<u8>,
+    /// `1` (default) for `initialize_mint`, `2` for `initialize_mint2`, which
+    /// does not take the rent sysvar account.
+    version: Option<u8>,
+    /// `token` (default) or `token2022`.
+    program: Option<String>,
+    /// Token-2022 only.
+    #[serde(rename = "metadataPointer")]
+    metadata_pointer: Option<MetadataPointerConfig>,
+    /// Token-2022 only.
+    #[serde(rename = "transferFee")]
+    transfer_fee: Option<TransferFeeConfig>,
+    /// Token-2022 only: may close the mint once its supply is zero.
+    #[serde(rename = "closeAuthority")]
+    close_authority: Option<String>,
+    /// Token-2022 only: `frozen` or `initialized`, the state new token
+    /// accounts start in. `frozen` requires a `freezeAuthority`.
+    #[serde(rename = "defaultAccountState")]
+    default_account_state: Option<String>,
+    /// May freeze and thaw the mint's token accounts.
+    #[serde(rename = "freezeAuthority")]
+    freeze_authority: Option<String>,
+    /// Appends the invoked program to each returned instruction's accounts.
+    /// Single-instruction endpoints only; compiled messages list every program.
+    #[serde(default, rename = "includeProgramAccounts")]
+    #[schemars(skip)]
+    include_program_accounts: bool,
+    /// Attaches a static compute-unit estimate for the returned instructions.
+    /// Single-instruction endpoints only; operations reject it.
+    #[serde(default, rename = "includeComputeHint")]
+    #[schemars(skip)]
+    include_compute_hint: bool,
+}
+
+#[derive(Deserialize)]
+struct UpdateMetadataRequest {
+    mint: String,
+    /// The metadata's update authority, which must sign.
+    authority: String,
+    /// `name`, `symbol`, `uri`, or any other string for a custom key.
+    field: String,
+    value: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct MetadataPointerConfig {
+    authority: Option<String>,
+    #[serde(rename = "metadataAddress")]
+    metadata_address: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct TransferFeeConfig {
+    #[serde(rename = "transferFeeBasisPoints")]
+    transfer_fee_basis_points: u16,
+    #[serde(rename = "maximumFee")]
+    maximum_fee: u64,
+    #[serde(rename = "transferFeeConfigAuthority")]
+    transfer_fee_config_authority: Option<String>,
+    #[serde(rename = "withdrawWithheldAuthority")]
+    withdraw_withheld_authority: Option<String>,
 }
 
 #[derive(Serialize)]
-struct SerializableInstruction {
+struct CreateTokenResponse {
+    /// The `initialize_mint` (or `initialize_mint2`) instruction, kept at the top level for existing clients.
+    #[serde(flatten)]
+    instruction: SerializableInstruction,
+    /// The decimals actually applied, after any server default.
+    decimals: u8,
+    example: DecimalsExample,
+    /// Token-2022 only: every instruction in order, extension initializers first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<Vec<SerializableInstruction>>,
+    /// Token-2022 only: mint account size including extensions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    space: Option<usize>,
+    /// Covers every instruction in the mint setup.
+    #[serde(rename = "computeUnitHint", skip_serializing_if = "Option::is_none")]
+    compute_unit_hint: Option<ComputeUnitHint>,
+    /// Describes the top-level `initialize_mint` instruction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<InstructionMeta>,
+}
+
+/// Heuristic compute-unit budget for a set of instructions. `heuristic` is
+/// always `true`: the figure comes from per-instruction-type defaults, not a
+/// simulation, so treat it as a starting point for `set_compute_unit_limit`.
+#[derive(Serialize)]
+struct ComputeUnitHint {
+    units: u32,
+    heuristic: bool,
+}
+
+impl ComputeUnitHint {
+    fn for_instructions<'a>(instructions: impl IntoIterator<Item = &'a Instruction>) -> Self {
+        ComputeUnitHint {
+            units: instructions.into_iter().map(estimate_compute_units).sum(),
+            heuristic: true,
+        }
+    }
+}
+
+/// A single instruction, optionally with its compute-unit hint.
+#[derive(Serialize)]
+struct InstructionResponse {
+    #[serde(flatten)]
+    instruction: SerializableInstruction,
+    #[serde(rename = "computeUnitHint", skip_serializing_if = "Option::is_none")]
+    compute_unit_hint: Option<ComputeUnitHint>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<InstructionMeta>,
+}
+
+/// Which builder produced an instruction, returned with `?includeMeta=true`
+/// to trace unexpected instructions back to the code that made them.
+#[derive(Serialize)]
+struct InstructionMeta {
+    /// e.g. `spl_token::initialize_mint`.
+    builder: &'static str,
+    #[serde(rename = "programId")]
     program_id: String,
-    accounts: Vec<SerializableAccountMeta>,
-    instruction_data: String,
 }
 
+impl InstructionMeta {
+    /// `Some` only when the request asked for it with `?includeMeta=true`.
+    fn requested(uri: &Uri, builder: &'static str, instruction: &Instruction) -> Option<Self> {
+        query_flag(uri, "includeMeta").then(|| InstructionMeta {
+            builder,
+            program_id: instruction.program_id.to_string(),
+        })
+    }
+}
+
+/// The instructions that initialize a mint and the account size they need.
+struct MintSetup {
+    instructions: Vec<Instruction>,
+    space: usize,
+    decimals: u8,
+    /// The builder of the final `initialize_mint` instruction.
+    builder: &'static str,
+}
+
+/// Reminds clients how raw amounts scale with the mint's decimals.
 #[derive(Serialize)]
-struct SerializableAccountMeta {
-    pubkey: String,
-    is_signer: bool,
-    is_writable: bool,
+struct DecimalsExample {
+    /// `null` when `10^decimals` does not fit in a u64.
+    #[serde(rename = "rawPerWholeToken")]
+    raw_per_whole_token: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct DisableMintAuthorityRequest {
+    mint: String,
+    /// The mint authority being given up; signs the instruction.
+    #[serde(rename = "currentAuthority")]
+    current_authority: String,
+    /// `token` (default) or `token2022`.
+    program: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct MintTokenRequest {
+    mint: String,
+    destination: String,
+    authority: String,
+    amount: u64,
+    /// Appends the invoked program to each returned instruction's accounts.
+    /// Single-instruction endpoints only; compiled messages list every program.
+    #[serde(default, rename = "includeProgramAccounts")]
+    #[schemars(skip)]
+    include_program_accounts: bool,
+    /// Attaches a static compute-unit estimate for the returned instructions.
+    /// Single-instruction endpoints only; operations reject it.
+    #[serde(default, rename = "includeComputeHint")]
+    #[schemars(skip)]
+    include_compute_hint: bool,
+}
+
+#[derive(Deserialize)]
+struct MintBatchRequest {
+    mint: String,
+    /// The mint authority; signs every `mint_to` and, with `resolveAta`, pays
+    /// for any ATA that has to be created.
+    authority: String,
+    recipients: Vec<MintRecipient>,
+    /// Treat each `destination` as a wallet and mint to its ATA, creating it
+    /// (idempotently) first.
+    #[serde(default, rename = "resolveAta")]
+    resolve_ata: bool,
+}
+
+#[derive(Deserialize)]
+struct MintRecipient {
+    destination: String,
+    amount: u64,
+}
+
+#[derive(Deserialize)]
+struct SignMessageRequest {
+    message: String,
+    secret: String,
+}
+
+#[derive(Serialize)]
+struct SignMessageResponse {
+    signature: String,
+    public_key: String,
+    message: String,
+}
+
+/// An off-chain authorization; see `Permit::payload` for the signed bytes.
+#[derive(Deserialize)]
+struct Permit {
+    domain: String,
+    nonce: u64,
+    /// Unix seconds after which the permit is no longer honoured.
+    expiry: u64,
+    action: String,
+}
+
+#[derive(Deserialize)]
+struct SignPermitRequest {
+    #[serde(flatten)]
+    permit: Permit,
+    secret: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyPermitRequest {
+    #[serde(flatten)]
+    permit: Permit,
+    /// Base64 signature returned by `/message/sign-permit`.
+    signature: String,
+    pubkey: String,
+}
+
+#[derive(Serialize)]
+struct VerifyPermitResponse {
+    /// The signature checks out and the permit has not expired.
+    valid: bool,
+    expired: bool,
+}
+
+#[derive(Serialize)]
+struct SignPermitResponse {
+    /// Base64 signature over `payload`.
+    signature: String,
+    /// Base64 canonical payload that was signed.
+    payload: String,
+    pubkey: String,
+}
+
+#[derive(Serialize)]
+struct SignOffchainResponse {
+    signature: String,
+    public_key: String,
+    /// Base64 of the serialized off-chain message envelope that was signed.
+    envelope: String,
+    /// `restrictedAscii`, `limitedUtf8` or `extendedUtf8`, chosen from the
+    /// message contents and length.
+    format: &'static str,
+}
+
+#[derive(Deserialize)]
+struct HashMessageRequest {
+    message: String,
+    /// `sha256` or `keccak256`.
+    algorithm: String,
+}
+
+#[derive(Serialize)]
+struct HashMessageResponse {
+    algorithm: String,
+    /// Hex digest of the message's UTF-8 bytes.
+    digest: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyMessageRequest {
+    message: String,
+    signature: String,
+    pubkey: String,
+}
+
+#[derive(Serialize)]
+struct VerifyMessageResponse {
+    valid: bool,
+    message: String,
+    pubkey: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyAnyRequest {
+    message: String,
+    signature: String,
+    /// Accepted signers, tried in order.
+    pubkeys: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct VerifyAnyResponse {
+    valid: bool,
+    /// The first listed pubkey whose signature matched.
+    #[serde(rename = "matchedPubkey")]
+    matched_pubkey: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VerifyBatchRequest {
+    items: Vec<VerifyMessageRequest>,
+}
+
+#[derive(Serialize)]
+struct VerifyBatchResponse {
+    #[serde(rename = "allValid")]
+    all_valid: bool,
+    /// Validity of each item, in request order.
+    results: Vec<bool>,
+}
+
+#[derive(Deserialize)]
+struct ConvertSignatureRequest {
+    signature: String,
+    from: String,
+    to: String,
+}
+
+#[derive(Serialize)]
+struct ConvertSignatureResponse {
+    signature: String,
+    encoding: &'static str,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SendSolRequest {
+    from: String,
+    to: String,
+    lamports: u64,
+    /// Appends the invoked program to each returned instruction's accounts.
+    /// Single-instruction endpoints only; compiled messages list every program.
+    #[serde(default, rename = "includeProgramAccounts")]
+    #[schemars(skip)]
+    include_program_accounts: bool,
+    /// Attaches a static compute-unit estimate for the returned instructions.
+    /// Single-instruction endpoints only; operations reject it.
+    #[serde(default, rename = "includeComputeHint")]
+    #[schemars(skip)]
+    include_compute_hint: bool,
+    /// `/send/sol` only, since it needs RPC: when the recipient does not exist
+    /// yet, raises `lamports` to the rent-exempt minimum for a system account.
+    #[serde(default, rename = "ensureRentExempt")]
+    #[schemars(skip)]
+    ensure_rent_exempt: bool,
+}
+
+#[derive(Serialize)]
+struct SendSolResponse {
+    #[serde(flatten)]
+    instruction: InstructionResponse,
+    /// Present when `ensureRentExempt` was set.
+    #[serde(rename = "rentExempt", skip_serializing_if = "Option::is_none")]
+    rent_exempt: Option<RentExemptCheck>,
+}
+
+#[derive(Serialize)]
+struct RentExemptCheck {
+    #[serde(rename = "recipientExists")]
+    recipient_exists: bool,
+    #[serde(rename = "minimumLamports")]
+    minimum_lamports: u64,
+    /// Whether `lamports` was raised to `minimumLamports`.
+    adjusted: bool,
+    /// What the instruction actually transfers.
+    lamports: u64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SendTokenRequest {
+    destination: String,
+    mint: String,
+    owner: String,
+    amount: u64,
+    /// Appends the invoked program to each returned instruction's accounts.
+    /// Single-instruction endpoints only; compiled messages list every program.
+    #[serde(default, rename = "includeProgramAccounts")]
+    #[schemars(skip)]
+    include_program_accounts: bool,
+    /// Attaches a static compute-unit estimate for the returned instructions.
+    /// Single-instruction endpoints only; operations reject it.
+    #[serde(default, rename = "includeComputeHint")]
+    #[schemars(skip)]
+    include_compute_hint: bool,
+}
+
+#[derive(Deserialize)]
+struct PayUrlRequest {
+    recipient: String,
+    /// Decimal amount in SOL or in whole `splToken` units, e.g. `"1.5"`.
+    amount: Option<String>,
+    #[serde(rename = "splToken")]
+    spl_token: Option<String>,
+    /// Public key the payment transaction must include, used to find it later.
+    reference: Option<String>,
+    label: Option<String>,
+    message: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PayUrlResponse {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct PayVerifyRequest {
+    /// Base64 bincode-serialized transaction, signed or not.
+    transaction: String,
+    recipient: String,
+    amount: String,
+    #[serde(rename = "splToken")]
+    spl_token: Option<String>,
+    reference: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PayVerifyResponse {
+    valid: bool,
+    reason: String,
+}
+
+#[derive(Deserialize)]
+struct CheckPolicyRequest {
+    /// Base64 serialized legacy or v0 transaction.
+    transaction: String,
+    policy: TransactionPolicy,
+}
+
+/// Each rule applies only when given.
+#[derive(Deserialize)]
+struct TransactionPolicy {
+    /// Every instruction must invoke one of these programs.
+    #[serde(rename = "allowedPrograms")]
+    allowed_programs: Option<Vec<String>>,
+    /// SOL and token transfers, delegations, closed-account refunds and new
+    /// authorities must go to one of these addresses or, where the mint is
+    /// known, to one of their associated token accounts.
+    #[serde(rename = "allowedRecipients")]
+    allowed_recipients: Option<Vec<String>>,
+    /// Cap on the lamports the transaction moves through system transfers,
+    /// account creations and nonce withdrawals, summed over all of them.
+    #[serde(rename = "maxLamports")]
+    max_lamports: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct CheckPolicyResponse {
+    compliant: bool,
+    /// Lamports moved by system transfers, account creations and nonce withdrawals.
+    #[serde(rename = "totalLamports")]
+    total_lamports: u64,
+    violations: Vec<PolicyViolation>,
+}
+
+#[derive(Serialize)]
+struct PolicyViolation {
+    /// Index of the offending instruction; absent for transaction-wide rules.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instruction: Option<usize>,
+    /// `program`, `recipient`, `maxLamports` or `unrecognized`.
+    rule: &'static str,
+    detail: String,
+}
+
+#[derive(Deserialize)]
+struct TransactionSummaryRequest {
+    /// Base64 serialized legacy or v0 transaction.
+    transaction: String,
+}
+
+#[derive(Serialize)]
+struct TransactionSummaryResponse {
+    #[serde(rename = "feePayer")]
+    fee_payer: String,
+    /// One sentence per instruction, in order.
+    lines: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct SolPreflightRequest {
+    from: String,
+    lamports: u64,
+}
+
+#[derive(Serialize)]
+struct SolPreflightResponse {
+    balance: u64,
+    /// Base fee for the single signature a transfer needs.
+    #[serde(rename = "estimatedFee")]
+    estimated_fee: u64,
+    /// Rent-exempt minimum `from` must keep to stay open after the transfer.
+    #[serde(rename = "rentReserve")]
+    rent_reserve: u64,
+    /// `lamports + estimatedFee + rentReserve`.
+    required: u64,
+    sufficient: bool,
+    /// How many more lamports `from` needs; `0` when sufficient.
+    shortfall: u64,
+}
+
+#[derive(Deserialize)]
+struct TokenSplitRequest {
+    mint: String,
+    /// Signs every transfer; tokens come from their ATA for `mint`.
+    owner: String,
+    decimals: u8,
+    recipients: Vec<SplitRecipient>,
+}
+
+#[derive(Deserialize)]
+struct GatedTransferRequest {
+    /// Token account the tokens leave; must already be thawed.
+    source: String,
+    /// Token account thawed for the transfer and frozen again afterwards.
+    destination: String,
+    /// Owner of `source`; signs the transfer.
+    owner: String,
+    mint: String,
+    /// The mint's freeze authority; signs the thaw and the freeze.
+    authority: String,
+    amount: u64,
+    decimals: u8,
+    /// `token` (default) or `token2022`.
+    program: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SplitRecipient {
+    ata: String,
+    amount: u64,
+}
+
+#[derive(Serialize)]
+struct TokenSplitResponse {
+    instructions: Vec<SerializableInstruction>,
+    /// Sum of every recipient amount.
+    total: u64,
+}
+
+#[derive(Deserialize)]
+struct EstimateFeeRequest {
+    message: String,
+}
+
+#[derive(Serialize)]
+struct EstimateFeeResponse {
+    lamports: u64,
+    sol: f64,
+}
+
+/// One step of a provisioning batch, tagged by `type`.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum FundingOperation {
+    /// A new rent-exempt account of `space` bytes, signed for by its own keypair.
+    CreateAccount { space: u64 },
+    Transfer { lamports: u64 },
+}
+
+#[derive(Deserialize)]
+struct EstimateFundingRequest {
+    operations: Vec<FundingOperation>,
+    /// How many transactions the batch is split into; defaults to one per operation.
+    transactions: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct EstimateFundingResponse {
+    #[serde(rename = "rentLamports")]
+    rent_lamports: u64,
+    #[serde(rename = "transferLamports")]
+    transfer_lamports: u64,
+    #[serde(rename = "feeLamports")]
+    fee_lamports: u64,
+    /// `rentLamports + transferLamports + feeLamports`.
+    #[serde(rename = "totalLamports")]
+    total_lamports: u64,
+    sol: f64,
+}
+
+#[derive(Deserialize)]
+struct RentByTypeRequest {
+    /// One of the names in `ACCOUNT_TYPE_SIZES`.
+    #[serde(rename = "type")]
+    account_type: String,
+}
+
+#[derive(Serialize)]
+struct RentByTypeResponse {
+    #[serde(rename = "type")]
+    account_type: String,
+    /// Bytes of account data the type occupies.
+    space: usize,
+    /// From the cluster when RPC is configured, otherwise the default rent schedule.
+    lamports: u64,
+}
+
+#[derive(Deserialize)]
+struct BuildTransactionRequest {
+    #[serde(rename = "feePayer")]
+    fee_payer: String,
+    instructions: Vec<SerializableInstruction>,
+    /// When omitted the latest blockhash is fetched from the cluster.
+    blockhash: Option<String>,
+}
+
+/// Exactly one of `instructions` or `operations` must be given.
+#[derive(Deserialize)]
+struct CompileMessageRequest {
+    #[serde(rename = "feePayer")]
+    fee_payer: String,
+    instructions: Option<Vec<SerializableInstruction>>,
+    /// The same steps `/instructions/build` accepts.
+    operations: Option<Vec<Operation>>,
+    /// Required; nothing is fetched from the cluster.
+    blockhash: String,
+}
+
+#[derive(Deserialize)]
+struct BuildWithNonceRequest {
+    #[serde(rename = "feePayer")]
+    fee_payer: String,
+    #[serde(rename = "nonceAccount")]
+    nonce_account: String,
+    #[serde(rename = "nonceAuthority")]
+    nonce_authority: String,
+    /// The value currently stored in the nonce account; used as the blockhash.
+    nonce: String,
+    instructions: Vec<SerializableInstruction>,
+}
+
+#[derive(Deserialize)]
+struct CreateNonceRequest {
+    payer: String,
+    /// The new nonce account; signs its own creation.
+    #[serde(rename = "nonceAccount")]
+    nonce_account: String,
+    /// May advance, withdraw from and re-authorize the nonce account.
+    authority: String,
+    /// Must cover the rent-exempt minimum for a nonce account.
+    lamports: u64,
+}
+
+#[derive(Deserialize)]
+struct AuthorizeNonceRequest {
+    #[serde(rename = "nonceAccount")]
+    nonce_account: String,
+    /// Signs the instruction.
+    #[serde(rename = "currentAuthority")]
+    current_authority: String,
+    #[serde(rename = "newAuthority")]
+    new_authority: String,
+}
+
+#[derive(Serialize)]
+struct CreateNonceResponse {
+    /// `create_account` then `initialize_nonce_account`.
+    instructions: Vec<SerializableInstruction>,
+    /// From the cluster when RPC is configured, otherwise the default rent schedule.
+    #[serde(rename = "rentLamports")]
+    rent_lamports: u64,
+}
+
+#[derive(Serialize)]
+struct BuildTransactionResponse {
+    message: String,
+    blockhash: String,
+    #[serde(rename = "lastValidBlockHeight", skip_serializing_if = "Option::is_none")]
+    last_valid_block_height: Option<u64>,
+    /// Pubkeys that must sign, in the order of the transaction's signature slots.
+    #[serde(rename = "requiredSigners")]
+    required_signers: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct WrapSolRequest {
+    owner: String,
+    lamports: u64,
+    /// Pays for the wSOL associated token account if it has to be created.
+    funder: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct UnwrapSolRequest {
+    owner: String,
+    /// Receives the unwrapped lamports.
+    destination: String,
+    /// Prepends `sync_native`, so lamports sent straight to the account show
+    /// up in its token balance before it is closed.
+    #[serde(default, rename = "syncFirst")]
+    sync_first: bool,
+}
+
+#[derive(Deserialize)]
+struct AllocateAssignWithSeedRequest {
+    /// Must equal `create_with_seed(base, seed, owner)`.
+    account: String,
+    /// Signs both instructions.
+    base: String,
+    seed: String,
+    space: u64,
+    owner: String,
+}
+
+#[derive(Deserialize)]
+struct CreateAccountWithSeedRequest {
+    /// Funds the new account; signs.
+    from: String,
+    /// Signs, unless it is the same key as `from`.
+    base: String,
+    seed: String,
+    lamports: u64,
+    space: u64,
+    owner: String,
+}
+
+#[derive(Serialize)]
+struct CreateAccountWithSeedResponse {
+    /// `create_with_seed(base, seed, owner)`.
+    address: String,
+    instruction: SerializableInstruction,
+}
+
+#[derive(Deserialize)]
+struct CloseManyRequest {
+    owner: String,
+    /// Receives the reclaimed rent of every closed account.
+    #[serde(rename = "rentDestination")]
+    rent_destination: String,
+    /// Empty token accounts owned by `owner`, closed in this order.
+    accounts: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CreateNativeAccountRequest {
+    owner: String,
+    /// Pays the rent if the account has to be created.
+    funder: String,
+}
+
+#[derive(Serialize)]
+struct CreateNativeAccountResponse {
+    /// The owner's associated token account for the native mint.
+    ata: String,
+    instruction: SerializableInstruction,
+}
+
+/// Shared by the wrap and unwrap endpoints.
+#[derive(Serialize)]
+struct WsolResponse {
+    ata: String,
+    instructions: Vec<SerializableInstruction>,
+}
+
+#[derive(Deserialize)]
+struct DeriveAddressesRequest {
+    owner: String,
+    mint: String,
+}
+
+#[derive(Serialize)]
+struct DeriveAddressesResponse {
+    #[serde(rename = "classicAta")]
+    classic_ata: String,
+    #[serde(rename = "token2022Ata")]
+    token_2022_ata: String,
+}
+
+#[derive(Deserialize)]
+struct VerifyAtaRequest {
+    ata: String,
+    owner: String,
+    mint: String,
+    /// `token` (default) or `token2022`.
+    program: Option<String>,
+}
+
+#[derive(Serialize)]
+struct VerifyAtaResponse {
+    valid: bool,
+    /// The canonical ATA for `owner` and `mint` under `program`.
+    expected: String,
+}
+
+#[derive(Deserialize)]
+struct InitializeAccount2022Request {
+    /// The new token account; must already be allocated with `space` bytes
+    /// and owned by the Token-2022 program.
+    account: String,
+    mint: String,
+    owner: String,
+}
+
+#[derive(Serialize)]
+struct AccountSetupResponse {
+    instructions: Vec<SerializableInstruction>,
+    space: usize,
+}
+
+#[derive(Deserialize)]
+struct AtaStatusRequest {
+    owner: String,
+    mint: String,
+}
+
+#[derive(Serialize)]
+struct AtaStatusResponse {
+    address: String,
+    /// `null` when RPC is not configured.
+    exists: Option<bool>,
+    /// From the cluster when RPC is configured, otherwise the default rent schedule.
+    #[serde(rename = "rentLamports")]
+    rent_lamports: u64,
+}
+
+#[derive(Deserialize)]
+struct TokenBalancesRequest {
+    owner: String,
+    mints: Vec<String>,
+    /// `token` (default) or `token2022`; selects which ATAs are derived.
+    program: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TokenBalancesResponse {
+    balances: Vec<TokenBalance>,
+}
+
+#[derive(Serialize)]
+struct TokenBalance {
+    mint: String,
+    ata: String,
+    /// Raw amount; `0` when the ATA does not exist.
+    amount: u64,
+    exists: bool,
+}
+
+#[derive(Deserialize)]
+struct SolBalancesRequest {
+    pubkeys: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SolBalancesResponse {
+    balances: Vec<SolBalance>,
+}
+
+#[derive(Serialize)]
+struct SolBalance {
+    pubkey: String,
+    /// `0` when the account does not exist.
+    lamports: u64,
+    exists: bool,
+}
+
+#[derive(Deserialize)]
+struct ClassifyAccountRequest {
+    account: String,
+}
+
+#[derive(Serialize)]
+struct ClassifyAccountResponse {
+    exists: bool,
+    /// `NotFound` when the account does not exist; otherwise one of
+    /// `SystemAccount`, `NonceAccount`, `TokenMint`, `TokenAccount`,
+    /// `TokenMultisig`, `Token2022Mint`, `Token2022Account`, `ProgramAccount`,
+    /// `ProgramData`, `StakeAccount`, `VoteAccount`, `Sysvar` or `Unknown`.
+    classification: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    #[serde(rename = "dataLength", skip_serializing_if = "Option::is_none")]
+    data_length: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lamports: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct TokenAccountInfoRequest {
+    account: String,
+}
+
+#[derive(Serialize)]
+struct TokenAccountInfoResponse {
+    mint: String,
+    owner: String,
+    amount: u64,
+    delegate: Option<String>,
+    state: &'static str,
+}
+
+#[derive(Deserialize)]
+struct TransactionStatusRequest {
+    /// Base58 transaction signature, as returned on submission.
+    signature: String,
+}
+
+#[derive(Serialize)]
+struct TransactionStatusResponse {
+    /// `notFound`, `failed`, or the confirmation level reached: `processed`,
+    /// `confirmed` or `finalized`.
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slot: Option<u64>,
+    /// Omitted once the transaction is rooted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confirmations: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TransactionGetRequest {
+    /// Base58 transaction signature.
+    signature: String,
+    /// `json` (default) for decoded instructions, or `base64` for the raw transaction.
+    encoding: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TransactionGetResponse {
+    /// `notFound`; `pending` when the cluster has seen the transaction but it
+    /// has not reached the client's commitment yet; else `confirmed` or `finalized`.
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slot: Option<u64>,
+    #[serde(rename = "blockTime", skip_serializing_if = "Option::is_none")]
+    block_time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fee: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    /// With `json`: instructions with lookup-table accounts resolved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<Vec<SerializableInstruction>>,
+    /// With `base64`: the bincode-serialized transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    transaction: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MintInfoRequest {
+    mint: String,
+}
+
+#[derive(Serialize)]
+struct MintInfoResponse {
+    decimals: u8,
+    supply: u64,
+    #[serde(rename = "mintAuthority")]
+    mint_authority: Option<String>,
+    #[serde(rename = "freezeAuthority")]
+    freeze_authority: Option<String>,
+    #[serde(rename = "isInitialized")]
+    is_initialized: bool,
+}
+
+#[derive(Deserialize)]
+struct TokenSupplyRequest {
+    mint: String,
+}
+
+#[derive(Serialize)]
+struct TokenSupplyResponse {
+    /// Base units.
+    supply: u64,
+    decimals: u8,
+    /// `supply` shifted by `decimals`, without trailing zeros, e.g. `"1.5"`.
+    #[serde(rename = "uiSupply")]
+    ui_supply: String,
+    /// `token` or `token2022`.
+    program: &'static str,
+}
+
+#[derive(Deserialize)]
+struct SignTransactionRequest {
+    /// Base64 serialized message for `/sign`, or transaction for `/sign-partial`.
+    transaction: String,
+    secret: String,
+}
+
+#[derive(Serialize)]
+struct SignTransactionResponse {
+    /// Base64 serialized transaction.
+    transaction: String,
+    /// Whether every required signature is now present.
+    complete: bool,
+}
+
+#[derive(Deserialize)]
+struct DecodeInstructionRequest {
+    /// Base64 instruction data.
+    data: String,
+}
+
+#[derive(Serialize)]
+struct DecodedInstructionResponse {
+    instruction: &'static str,
+    fields: Value,
+}
+
+#[derive(Deserialize)]
+struct DecodeBatchRequest {
+    instructions: Vec<SerializableInstruction>,
+}
+
+#[derive(Serialize)]
+struct DecodeBatchResponse {
+    results: Vec<DecodedBatchEntry>,
+}
+
+/// One entry per input instruction. Known programs carry `instruction` and
+/// `fields`; anything else falls back to `rawData`, with `error` set when a
+/// known program's data could not be decoded.
+#[derive(Serialize)]
+struct DecodedBatchEntry {
+    #[serde(rename = "programId")]
+    program_id: String,
+    /// `system`, `token`, `token2022` or `unknown`.
+    program: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instruction: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<Value>,
+    /// Hex instruction data, for entries that were not decoded.
+    #[serde(rename = "rawData", skip_serializing_if = "Option::is_none")]
+    raw_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// One step of a `/instructions/build` request, tagged by `type` and carrying
+/// the same fields as the matching single-instruction endpoint. Its JSON schema
+/// is what `/operations` reports, so each variant lists the programs it calls.
+#[derive(Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Operation {
+    #[schemars(extend("programs" = ["token", "token2022"]))]
+    CreateToken(CreateTokenRequest),
+    #[schemars(extend("programs" = ["token"]))]
+    MintTo(MintTokenRequest),
+    #[schemars(extend("programs" = ["system"]))]
+    TransferSol(SendSolRequest),
+    #[schemars(extend("programs" = ["token"]))]
+    TransferToken(SendTokenRequest),
+    #[schemars(extend("programs" = ["associatedToken", "system", "token"]))]
+    WrapSol(WrapSolRequest),
+    #[schemars(extend("programs" = ["token"]))]
+    UnwrapSol(UnwrapSolRequest),
+}
+
+#[derive(Serialize)]
+struct OperationsResponse {
+    operations: Vec<OperationSchema>,
+}
+
+#[derive(Serialize)]
+struct OperationSchema {
+    /// The `type` tag to send.
+    name: String,
+    /// `system`, `token`, `token2022` or `associatedToken`.
+    programs: Vec<String>,
+    /// Required fields first, each group in alphabetical order.
+    fields: Vec<OperationField>,
+}
+
+#[derive(Serialize)]
+struct OperationField {
+    name: String,
+    /// `string`, `bool`, an integer width such as `u64`, or `object`.
+    #[serde(rename = "type")]
+    field_type: String,
+    required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BuildInstructionsRequest {
+    operations: Vec<Operation>,
+}
+
+#[derive(Serialize)]
+struct InstructionListResponse {
+    instructions: Vec<SerializableInstruction>,
+}
+
+/// Exactly one of `transaction` or `message` must be given.
+#[derive(Deserialize)]
+struct TransactionSizeRequest {
+    /// Base64 serialized transaction.
+    transaction: Option<String>,
+    /// Base64 serialized message; signature space for its required signers is added.
+    message: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TransactionSizeResponse {
+    bytes: usize,
+    limit: usize,
+    fits: bool,
+}
+
+/// The camelCase counterpart of `SerializableInstruction`, for `/instruction/custom`.
+#[derive(Deserialize)]
+struct CustomInstructionRequest {
+    #[serde(rename = "programId")]
+    program_id: String,
+    /// Passed through in order, duplicates included.
+    accounts: Vec<CustomAccountMeta>,
+    /// Base64 instruction data.
+    data: String,
+    /// Annotates each account with its position in `accountKeys`.
+    #[serde(default, rename = "withIndices")]
+    with_indices: bool,
+    /// The compiled message's account keys in order; required with `withIndices`.
+    #[serde(rename = "accountKeys")]
+    account_keys: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct CustomInstructionResponse {
+    #[serde(flatten)]
+    instruction: SerializableInstruction,
+    /// Position of `program_id` in `accountKeys`; present with `withIndices`.
+    #[serde(rename = "programIdIndex", skip_serializing_if = "Option::is_none")]
+    program_id_index: Option<Option<usize>>,
+    /// The accounts in instruction order with their `accountKeys` positions;
+    /// present with `withIndices`.
+    #[serde(rename = "indexedAccounts", skip_serializing_if = "Option::is_none")]
+    indexed_accounts: Option<Vec<IndexedAccountMeta>>,
+}
+
+/// An entry of `accounts` with the same keys, plus its `accountKeys` position.
+#[derive(Serialize)]
+struct IndexedAccountMeta {
+    #[serde(flatten)]
+    meta: SerializableAccountMeta,
+    /// `null` when the key is missing from `accountKeys`, which on chain
+    /// surfaces as an account index out of bounds.
+    index: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct CustomAccountMeta {
+    pubkey: String,
+    #[serde(rename = "isSigner")]
+    is_signer: bool,
+    #[serde(rename = "isWritable")]
+    is_writable: bool,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct SerializableInstruction {
+    program_id: String,
+    accounts: Vec<SerializableAccountMeta>,
+    instruction_data: String,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct SerializableAccountMeta {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+impl From<Instruction> for SerializableInstruction {
+    fn from(instruction: Instruction) -> Self {
+        SerializableInstruction {
+            program_id: instruction.program_id.to_string(),
+            accounts: instruction
+                .accounts
+                .into_iter()
+                .map(SerializableAccountMeta::from)
+                .collect(),
+            instruction_data: general_purpose::STANDARD.encode(&instruction.data),
+        }
+    }
+}
+
+impl SerializableInstruction {
+    /// Converts `instruction`, optionally listing its program id as a trailing
+    /// read-only account so clients assembling transactions have the full set.
+    /// The program is the only implicit account: sysvars such as rent and the
+    /// programs reached by CPI, like the system and token programs for an
+    /// associated token account, are already among the builders' metas.
+    fn expanded(instruction: Instruction, include_program_accounts: bool) -> Self {
+        let program_id = instruction.program_id.to_string();
+        let mut serialized = SerializableInstruction::from(instruction);
+        if include_program_accounts && !serialized.accounts.iter().any(|meta| meta.pubkey == program_id) {
+            serialized.accounts.push(SerializableAccountMeta {
+                pubkey: program_id,
+                is_signer: false,
+                is_writable: false,
+            });
+        }
+        serialized
+    }
+
+    /// Rebuilds the instruction, recording failures under `field` (or at the
+    /// top level when `field` is empty).
+    fn to_instruction(&self, field: &str, validation: &mut Validation) -> Instruction {
+        let name = |suffix: &str| match field {
+            "" => suffix.to_string(),
+            field => format!("{}.{}", field, suffix),
+        };
+        let program_id = validation.pubkey(
+            &name("program_id"),
+            &self.program_id,
+            "Invalid instruction program id",
+        );
+        let accounts = self
+            .accounts
+            .iter()
+            .enumerate()
+            .map(|(i, meta)| AccountMeta {
+                pubkey: validation.pubkey(
+                    &name(&format!("accounts[{}].pubkey", i)),
+                    &meta.pubkey,
+                    "Invalid instruction account public key",
+                ),
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect();
+        let data = match general_purpose::STANDARD.decode(&self.instruction_data) {
+            Ok(data) => data,
+            Err(_) => {
+                validation.fail(
+                    &name("instruction_data"),
+                    "must be base64",
+                    "Invalid instruction data; must be base64",
+                );
+                Vec::new()
+            }
+        };
+        Instruction { program_id, accounts, data }
+    }
+}
+
+impl From<VersionedTransaction> for SignTransactionResponse {
+    fn from(transaction: VersionedTransaction) -> Self {
+        SignTransactionResponse {
+            complete: transaction.signatures.iter().all(|sig| *sig != Signature::default()),
+            transaction: general_purpose::STANDARD.encode(
+                bincode::serialize(&transaction).expect("transaction serialization is infallible"),
+            ),
+        }
+    }
+}
+
+impl From<AccountMeta> for SerializableAccountMeta {
+    fn from(meta: AccountMeta) -> Self {
+        SerializableAccountMeta {
+            pubkey: meta.pubkey.to_string(),
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        }
+    }
+}
+
+/// In-memory request counters, reset on restart.
+struct RequestStats {
+    started_at: Instant,
+    total: AtomicU64,
+    client_errors: AtomicU64,
+    server_errors: AtomicU64,
+    /// Keyed by matched route, so unknown paths cannot grow the map.
+    routes: RwLock<HashMap<String, RouteCounters>>,
+}
+
+#[derive(Default)]
+struct RouteCounters {
+    requests: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl RequestStats {
+    fn new() -> Self {
+        RequestStats {
+            started_at: Instant::now(),
+            total: AtomicU64::new(0),
+            client_errors: AtomicU64::new(0),
+            server_errors: AtomicU64::new(0),
+            routes: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn record_route(&self, route: &str, is_error: bool) {
+        let routes = self.routes.read().unwrap();
+        if let Some(counters) = routes.get(route) {
+            counters.requests.fetch_add(1, Ordering::Relaxed);
+            if is_error {
+                counters.errors.fetch_add(1, Ordering::Relaxed);
+            }
+            return;
+        }
+        drop(routes);
+
+        let mut routes = self.routes.write().unwrap();
+        let counters = routes.entry(route.to_string()).or_default();
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        if is_error {
+            counters.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A small TTL cache of fetched accounts, keyed by address. Only accounts that
+/// exist are cached so a freshly created one is visible on the next lookup.
+struct AccountCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<Pubkey, (Instant, Account)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl AccountCache {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        AccountCache {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the cached account, or fetches it with the client's commitment
+    /// and remembers it. A zero TTL or capacity disables caching.
+    async fn get(&self, rpc: &RpcClient, address: &Pubkey) -> Result<Option<Account>, ClientError> {
+        if let Some((fetched_at, account)) = self.entries.lock().await.get(address)
+            && fetched_at.elapsed() < self.ttl
+        {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(account.clone()));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let account = rpc.get_account_with_commitment(address, rpc.commitment()).await?.value;
+        if let Some(account) = &account
+            && self.capacity > 0
+            && !self.ttl.is_zero()
+        {
+            let mut entries = self.entries.lock().await;
+            if entries.len() >= self.capacity && !entries.contains_key(address) {
+                entries.retain(|_, (fetched_at, _)| fetched_at.elapsed() < self.ttl);
+            }
+            if entries.len() >= self.capacity
+                && let Some(oldest) = entries.iter().min_by_key(|(_, (fetched_at, _))| *fetched_at).map(|(key, _)| *key)
+            {
+                entries.remove(&oldest);
+            }
+            entries.insert(*address, (Instant::now(), account.clone()));
+        }
+        Ok(account)
+    }
+
+    async fn counters(&self) -> CacheCountersResponse {
+        CacheCountersResponse {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().await.len(),
+        }
+    }
+}
+
+/// Responses smaller than this are sent uncompressed; the framing overhead
+/// outweighs any saving.
+const COMPRESSION_MIN_BYTES: u16 = 1024;
+
+/// Longest metadata value accepted; leaves room in a single packet for the
+/// signatures, account keys and the rest of the instruction.
+const MAX_METADATA_VALUE_LEN: usize = 1_000;
+
+/// Most `close_account` instructions that fit in one transaction signed only by
+/// the owner: 198 fixed bytes (signature, header, owner, destination, token
+/// program, blockhash) plus 39 per account (its key and a 7-byte instruction)
+/// must stay within the 1232-byte packet limit.
+const MAX_CLOSE_ACCOUNTS: usize = 26;
+
+/// Most `mint_to` instructions that fit in one transaction signed only by the
+/// authority: 198 fixed bytes (signature, header, authority, mint, token
+/// program, blockhash) plus 47 per recipient (its token account and a 15-byte
+/// instruction) must stay within the 1232-byte packet limit.
+const MAX_MINT_BATCH: usize = 22;
+
+/// As `MAX_MINT_BATCH` when each recipient's ATA is also created: the system
+/// and ATA programs raise the fixed part to 262 bytes, and each recipient then
+/// costs 89 (wallet, ATA, and a 10-byte create instruction on top).
+const MAX_MINT_BATCH_WITH_ATA: usize = 10;
+
+/// `getMultipleAccounts` takes at most 100 keys, which bounds `MAX_BATCH_ACCOUNTS`.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Default `MAX_BATCH_TRANSFERS`. Far more than fit in one transaction;
+/// callers chunk the returned instructions themselves.
+const DEFAULT_MAX_BATCH_TRANSFERS: usize = 500;
+
+/// Default `MAX_BATCH_DECODE`: most instructions `/instruction/decode-batch` accepts.
+const DEFAULT_MAX_BATCH_DECODE: usize = 256;
+
+/// Default `MAX_BATCH_FUNDING_OPERATIONS`: most operations `/estimate/funding` accepts.
+const DEFAULT_MAX_BATCH_FUNDING_OPERATIONS: usize = 1_000;
+
+/// Default `MAX_BATCH_SIGNATURES`: most signatures `/message/verify-batch-fast`
+/// checks per request.
+const DEFAULT_MAX_BATCH_SIGNATURES: usize = 10_000;
+
+/// Default `MAX_BATCH_KEYPAIRS`: most keypairs `/keypair/hd-batch` derives per request.
+const DEFAULT_MAX_BATCH_KEYPAIRS: usize = 100;
+
+/// Longest vanity pattern searched for; each extra character multiplies the
+/// expected work by 58, and five characters never finish within the timeout.
+const MAX_VANITY_PATTERN_LEN: usize = 4;
+
+/// Default for `VANITY_CONCURRENCY`. Each search pins a blocking thread and a
+/// core for up to the timeout.
+const DEFAULT_VANITY_CONCURRENCY: usize = 2;
+
+const VANITY_PREFIX_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Suffix and substring searches get a longer cap than prefixes.
+const VANITY_SUFFIX_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Shortest passphrase `/keypair/encrypted` accepts, in characters.
+const MIN_PASSPHRASE_LEN: usize = 12;
+/// Fewest distinct characters a passphrase may use, which turns away
+/// `aaaaaaaaaaaa` and friends.
+const MIN_PASSPHRASE_DISTINCT_CHARS: usize = 5;
+/// Only encryption scheme `/keypair/encrypted` emits; see `EncryptedSecret`.
+const ENCRYPTED_SECRET_VERSION: u8 = 1;
+const ENCRYPTED_SECRET_SALT_LEN: usize = 16;
+const ENCRYPTED_SECRET_NONCE_LEN: usize = 24;
+/// Default for `SCRYPT_CONCURRENCY`. Each derivation holds about 32 MiB and a
+/// blocking thread, so only a few run at once.
+const DEFAULT_SCRYPT_CONCURRENCY: usize = 4;
+
+/// Account types `/rent/by-type` knows the size of. Token-2022 sizes are for
+/// accounts without extensions.
+const ACCOUNT_TYPE_SIZES: &[(&str, usize)] = &[
+    ("system", 0),
+    ("mint", spl_token::state::Mint::LEN),
+    ("tokenAccount", spl_token::state::Account::LEN),
+    ("multisig", spl_token::state::Multisig::LEN),
+    ("mint2022", spl_token_2022::state::Mint::LEN),
+    ("tokenAccount2022", spl_token_2022::state::Account::LEN),
+    ("nonce", solana_sdk::nonce::state::State::size()),
+    ("stake", solana_sdk::stake::state::StakeStateV2::size_of()),
+];
+
+/// The path Phantom, Solflare and `solana-keygen` use for account `{i}`.
+const DEFAULT_HD_PATH_TEMPLATE: &str = "m/44'/501'/{i}'/0'";
+
+const STAKE_PROGRAM_ID: Pubkey = pubkey!("Stake11111111111111111111111111111111111111");
+const VOTE_PROGRAM_ID: Pubkey = pubkey!("Vote111111111111111111111111111111111111111");
+const SYSVAR_OWNER_ID: Pubkey = pubkey!("Sysvar1111111111111111111111111111111111111");
+const UPGRADEABLE_LOADER_ID: Pubkey = pubkey!("BPFLoaderUpgradeab1e11111111111111111111111");
+
+/// The cluster's base fee per signature; priority fees come on top.
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+/// Largest decimals value we accept for a new mint.
+const MAX_TOKEN_DECIMALS: u8 = 9;
+
+/// Longest wait between RPC retries, however many attempts `RPC_MAX_RETRIES`
+/// allows; the doubling would otherwise reach minutes within a dozen retries.
+const MAX_RPC_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Pause after a failed `accept`. Errors such as running out of file
+/// descriptors persist until a connection closes, so retrying at once spins.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// One operator-tunable cap and the env var that sets it.
+#[derive(Clone, Copy, Debug)]
+struct BatchLimit {
+    max: usize,
+    env: &'static str,
+}
+
+impl BatchLimit {
+    /// Reads `env`, falling back to `default`. A configured value above
+    /// `ceiling`, where the cluster or packet size imposes one, is refused.
+    fn from_env(env: &'static str, default: usize, ceiling: Option<usize>) -> Self {
+        let max = match std::env::var(env) {
+            Ok(value) => value
+                .parse()
+                .ok()
+                .filter(|max| *max > 0)
+                .unwrap_or_else(|| panic!("{} must be a positive integer", env)),
+            Err(_) => default,
+        };
+        if let Some(ceiling) = ceiling {
+            assert!(max <= ceiling, "{} must be at most {}", env, ceiling);
+        }
+        BatchLimit { max, env }
+    }
+}
+
+/// Per-request caps on the batch endpoints, read once at startup.
+#[derive(Clone, Copy, Debug)]
+struct BatchLimits {
+    /// `MAX_BATCH_KEYPAIRS`: `/keypair/hd-batch`.
+    keypairs: BatchLimit,
+    /// `MAX_BATCH_TRANSFERS`: `/send/token-split`.
+    transfers: BatchLimit,
+    /// `MAX_BATCH_MINTS`: `/token/mint-batch`; at most `MAX_MINT_BATCH`.
+    mints: BatchLimit,
+    /// `MAX_BATCH_ACCOUNTS`: `/account/balances` and `/token/balances`; at
+    /// most `MAX_MULTIPLE_ACCOUNTS`.
+    accounts: BatchLimit,
+    /// `MAX_BATCH_DECODE`: `/instruction/decode-batch`.
+    decode: BatchLimit,
+    /// `MAX_BATCH_SIGNATURES`: `/message/verify-batch-fast`.
+    signatures: BatchLimit,
+    /// `MAX_BATCH_CLOSE_ACCOUNTS`: `/token/close-many`; at most `MAX_CLOSE_ACCOUNTS`.
+    close_accounts: BatchLimit,
+    /// `MAX_BATCH_FUNDING_OPERATIONS`: `/estimate/funding`.
+    funding_operations: BatchLimit,
+}
+
+impl BatchLimits {
+    fn from_env() -> Self {
+        BatchLimits {
+            keypairs: BatchLimit::from_env("MAX_BATCH_KEYPAIRS", DEFAULT_MAX_BATCH_KEYPAIRS, None),
+            transfers: BatchLimit::from_env("MAX_BATCH_TRANSFERS", DEFAULT_MAX_BATCH_TRANSFERS, None),
+            mints: BatchLimit::from_env("MAX_BATCH_MINTS", MAX_MINT_BATCH, Some(MAX_MINT_BATCH)),
+            accounts: BatchLimit::from_env("MAX_BATCH_ACCOUNTS", MAX_MULTIPLE_ACCOUNTS, Some(MAX_MULTIPLE_ACCOUNTS)),
+            decode: BatchLimit::from_env("MAX_BATCH_DECODE", DEFAULT_MAX_BATCH_DECODE, None),
+            signatures: BatchLimit::from_env("MAX_BATCH_SIGNATURES", DEFAULT_MAX_BATCH_SIGNATURES, None),
+            close_accounts: BatchLimit::from_env("MAX_BATCH_CLOSE_ACCOUNTS", MAX_CLOSE_ACCOUNTS, Some(MAX_CLOSE_ACCOUNTS)),
+            funding_operations: BatchLimit::from_env(
+                "MAX_BATCH_FUNDING_OPERATIONS",
+                DEFAULT_MAX_BATCH_FUNDING_OPERATIONS,
+                None,
+            ),
+        }
+    }
+
+    /// The most elements any batch endpoint accepts; the bound for `limit_json_arrays`.
+    fn largest(&self) -> usize {
+        [
+            self.keypairs,
+            self.transfers,
+            self.mints,
+            self.accounts,
+            self.decode,
+            self.signatures,
+            self.close_accounts,
+            self.funding_operations,
+        ]
+        .iter()
+            .map(|limit| limit.max)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    /// `None` when `RPC_URL` is unset; RPC-backed endpoints then answer 503.
+    rpc: Option<Arc<RpcClient>>,
+    /// Set by `ENABLE_SERVER_SIGNING`; lets clients hand us secret keys to sign with.
+    server_signing: bool,
+    /// Set by `READ_ONLY`; disables endpoints that sign or touch cluster state.
+    read_only: bool,
+    /// How long a `/cluster/info` snapshot is served before RPC is asked again.
+    cluster_info_ttl: Duration,
+    cluster_info_cache: Arc<Mutex<Option<(Instant, ClusterInfoResponse)>>>,
+    /// `MINT_CACHE_TTL_MS`: mints rarely change, so `/token/mint-info` caches longer.
+    mint_cache: Arc<AccountCache>,
+    /// `ACCOUNT_CACHE_TTL_MS`: balances move, so `/token/account-info` caches briefly.
+    account_cache: Arc<AccountCache>,
+    stats: Arc<RequestStats>,
+    /// `REQUEST_TIMEOUT_MS`: server-side cap on handling time, if any.
+    request_timeout: Option<Duration>,
+    /// `API_HMAC_SECRET`: when set, state-changing endpoints require a signed
+    /// `Authorization` header.
+    hmac_secret: Option<Arc<Vec<u8>>>,
+    /// `API_HMAC_MAX_SKEW_SECS`: how far a signed timestamp may be from now.
+    hmac_max_skew: Duration,
+    /// `DEFAULT_TOKEN_DECIMALS`: used when `/token/create` omits `decimals`.
+    default_decimals: u8,
+    /// `TEST_MODE`: enables deterministic test helpers such as `entropyHex`.
+    test_mode: bool,
+    batch_limits: BatchLimits,
+    /// `SCRYPT_CONCURRENCY`: permits for passphrase key derivations in flight.
+    scrypt_permits: Arc<Semaphore>,
+    /// `VANITY_CONCURRENCY`: permits for vanity searches in flight.
+    vanity_permits: Arc<Semaphore>,
+}
+
+fn rpc_client(state: &AppState) -> Result<&RpcClient, (StatusCode, Json<ErrorResponse>)> {
+    state.rpc.as_deref().ok_or((
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ErrorResponse::new("RPC is not configured; set RPC_URL")),
+    ))
+}
+
+/// Wraps the HTTP transport so every RPC call retries transient failures
+/// (timeouts, connection errors, 429, 5xx, unhealthy node) with exponential
+/// backoff. Deterministic errors are returned immediately.
+struct RetrySender {
+    inner: HttpSender,
+    /// `RPC_MAX_RETRIES`: retries after the first attempt.
+    max_retries: u32,
+    /// `RPC_BACKOFF_MS`: delay before the first retry, doubled each time up to
+    /// `MAX_RPC_BACKOFF`.
+    backoff: Duration,
+}
+
+fn is_transient(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.is_request()
+                || e.status().is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+        }
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        _ => false,
+    }
+}
+
+#[async_trait::async_trait]
+impl RpcSender for RetrySender {
+    async fn send(&self, request: RpcRequest, params: Value) -> Result<Value, ClientError> {
+        let mut attempt = 0;
+        loop {
+            match self.inner.send(request, params.clone()).await {
+                Err(e) if attempt < self.max_retries && is_transient(&e) => {
+                    let delay = self.backoff.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_RPC_BACKOFF);
+                    attempt += 1;
+                    tracing::warn!(method = %request, attempt, delay_ms = delay.as_millis() as u64, error = %e, "retrying RPC request");
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+fn rpc_error(e: ClientError) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_GATEWAY,
+        Json(ErrorResponse::new(&format!("RPC request failed: {}", e))),
+    )
+}
+
+fn decode_message(encoded: &str) -> Result<VersionedMessage, (StatusCode, Json<ErrorResponse>)> {
+    let bytes = general_purpose::STANDARD.decode(encoded).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("Invalid message format; must be base64")),
+        )
+    })?;
+    // Sanitizing rejects headers and indices that point past the account keys,
+    // which everything downstream indexes by.
+    bincode::deserialize::<VersionedMessage>(&bytes)
+        .ok()
+        .filter(|message| message.sanitize().is_ok())
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid transaction message"))))
+}
+
+fn decode_transaction(encoded: &str) -> Result<VersionedTransaction, (StatusCode, Json<ErrorResponse>)> {
+    let bytes = general_purpose::STANDARD.decode(encoded).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("Invalid transaction format; must be base64")),
+        )
+    })?;
+    // Only the message is sanitized: partially signed transactions may still
+    // be missing signatures.
+    bincode::deserialize::<VersionedTransaction>(&bytes)
+        .ok()
+        .filter(|transaction| transaction.message.sanitize().is_ok())
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid transaction"))))
+}
+
+fn decode_keypair(secret: &str) -> Result<Keypair, (StatusCode, Json<ErrorResponse>)> {
+    match bs58::decode(secret).into_vec() {
+        Ok(bytes) => Keypair::try_from(bytes.as_slice())
+            .map_err(|_| (StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid secret key")))),
+        Err(_) => Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid secret key format")))),
+    }
+}
+
+/// Fills the signature slot belonging to `keypair`, leaving every other slot untouched.
+fn sign_transaction_slot(
+    transaction: &mut VersionedTransaction,
+    keypair: &Keypair,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let required = transaction.message.header().num_required_signatures as usize;
+    let pubkey = keypair.pubkey();
+    let signers = transaction.message.static_account_keys().get(..required).unwrap_or_default();
+    let index = match signers.iter().position(|key| *key == pubkey) {
+        Some(index) => index,
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new("Keypair is not a required signer of this transaction")),
+            ))
+        }
+    };
+    transaction.signatures.resize(required, Signature::default());
+    transaction.signatures[index] = keypair.sign_message(&transaction.message.serialize());
+    Ok(())
+}
+
+/// Reads a boolean feature flag; `1` and `true` enable it.
+fn env_flag(name: &str) -> bool {
+    matches!(std::env::var(name).as_deref(), Ok("1") | Ok("true"))
+}
+
+fn optional_pubkey(key: COption<Pubkey>) -> Value {
+    match key {
+        COption::Some(pk) => json!(pk.to_string()),
+        COption::None => Value::Null,
+    }
+}
+
+/// Names an SPL token instruction and lists its arguments in camelCase.
+fn describe_token_instruction(instruction: TokenInstruction) -> DecodedInstructionResponse {
+    let (name, fields) = match instruction {
+        TokenInstruction::InitializeMint { decimals, mint_authority, freeze_authority } => (
+            "InitializeMint",
+            json!({
+                "decimals": decimals,
+                "mintAuthority": mint_authority.to_string(),
+                "freezeAuthority": optional_pubkey(freeze_authority),
+            }),
+        ),
+        TokenInstruction::InitializeMint2 { decimals, mint_authority, freeze_authority } => (
+            "InitializeMint2",
+            json!({
+                "decimals": decimals,
+                "mintAuthority": mint_authority.to_string(),
+                "freezeAuthority": optional_pubkey(freeze_authority),
+            }),
+        ),
+        TokenInstruction::InitializeAccount => ("InitializeAccount", json!({})),
+        TokenInstruction::InitializeAccount2 { owner } => ("InitializeAccount2", json!({ "owner": owner.to_string() })),
+        TokenInstruction::InitializeAccount3 { owner } => ("InitializeAccount3", json!({ "owner": owner.to_string() })),
+        TokenInstruction::InitializeMultisig { m } => ("InitializeMultisig", json!({ "m": m })),
+        TokenInstruction::InitializeMultisig2 { m } => ("InitializeMultisig2", json!({ "m": m })),
+        TokenInstruction::Transfer { amount } => ("Transfer", json!({ "amount": amount })),
+        TokenInstruction::Approve { amount } => ("Approve", json!({ "amount": amount })),
+        TokenInstruction::Revoke => ("Revoke", json!({})),
+        TokenInstruction::SetAuthority { authority_type, new_authority } => (
+            "SetAuthority",
+            json!({
+                "authorityType": match authority_type {
+                    AuthorityType::MintTokens => "mintTokens",
+                    AuthorityType::FreezeAccount => "freezeAccount",
+                    AuthorityType::AccountOwner => "accountOwner",
+                    AuthorityType::CloseAccount => "closeAccount",
+                },
+                "newAuthority": optional_pubkey(new_authority),
+            }),
+        ),
+        TokenInstruction::MintTo { amount } => ("MintTo", json!({ "amount": amount })),
+        TokenInstruction::Burn { amount } => ("Burn", json!({ "amount": amount })),
+        TokenInstruction::CloseAccount => ("CloseAccount", json!({})),
+        TokenInstruction::FreezeAccount => ("FreezeAccount", json!({})),
+        TokenInstruction::ThawAccount => ("ThawAccount", json!({})),
+        TokenInstruction::TransferChecked { amount, decimals } => {
+            ("TransferChecked", json!({ "amount": amount, "decimals": decimals }))
+        }
+        TokenInstruction::ApproveChecked { amount, decimals } => {
+            ("ApproveChecked", json!({ "amount": amount, "decimals": decimals }))
+        }
+        TokenInstruction::MintToChecked { amount, decimals } => {
+            ("MintToChecked", json!({ "amount": amount, "decimals": decimals }))
+        }
+        TokenInstruction::BurnChecked { amount, decimals } => {
+            ("BurnChecked", json!({ "amount": amount, "decimals": decimals }))
+        }
+        TokenInstruction::SyncNative => ("SyncNative", json!({})),
+        TokenInstruction::GetAccountDataSize => ("GetAccountDataSize", json!({})),
+        TokenInstruction::InitializeImmutableOwner => ("InitializeImmutableOwner", json!({})),
+        TokenInstruction::AmountToUiAmount { amount } => ("AmountToUiAmount", json!({ "amount": amount })),
+        TokenInstruction::UiAmountToAmount { ui_amount } => ("UiAmountToAmount", json!({ "uiAmount": ui_amount })),
+    };
+    DecodedInstructionResponse { instruction: name, fields }
+}
+
+/// Rough compute units per instruction, from the figures the runtime and SPL
+/// programs document: builtins such as the system program charge a flat 150,
+/// token instructions land between roughly 3k and 6.2k, and ATA creation is
+/// around 25k. Token-2022 shares the token table and counts its extension
+/// instructions as a generic 5k; unknown programs fall back to the runtime's
+/// 200k per-instruction default.
+fn estimate_compute_units(instruction: &Instruction) -> u32 {
+    const DEFAULT_INSTRUCTION_UNITS: u32 = 200_000;
+    if instruction.program_id == solana_system_interface::program::ID {
+        return 150;
+    }
+    if instruction.program_id == spl_associated_token_account::ID {
+        return 25_000;
+    }
+    if instruction.program_id != spl_token::ID && instruction.program_id != spl_token_2022::ID {
+        return DEFAULT_INSTRUCTION_UNITS;
+    }
+    match TokenInstruction::unpack(&instruction.data) {
+        Ok(TokenInstruction::InitializeMint { .. }) => 2_967,
+        Ok(TokenInstruction::InitializeMint2 { .. }) => 2_827,
+        Ok(TokenInstruction::InitializeAccount) => 4_527,
+        Ok(TokenInstruction::InitializeAccount2 { .. } | TokenInstruction::InitializeAccount3 { .. }) => 4_240,
+        Ok(TokenInstruction::Transfer { .. }) => 4_645,
+        Ok(TokenInstruction::TransferChecked { .. }) => 6_200,
+        Ok(TokenInstruction::MintTo { .. } | TokenInstruction::MintToChecked { .. }) => 4_538,
+        Ok(TokenInstruction::Burn { .. } | TokenInstruction::BurnChecked { .. }) => 4_753,
+        Ok(TokenInstruction::CloseAccount) => 2_916,
+        Ok(TokenInstruction::SyncNative) => 3_045,
+        Ok(_) => 5_000,
+        Err(_) if spl_token_2022::instruction::TokenInstruction::unpack(&instruction.data).is_ok() => 5_000,
+        Err(_) => DEFAULT_INSTRUCTION_UNITS,
+    }
+}
+
+/// bincode ignores trailing bytes, so the variant must account for all of them.
+fn unpack_system_instruction(data: &[u8]) -> Option<SystemInstruction> {
+    bincode::deserialize::<SystemInstruction>(data)
+        .ok()
+        .filter(|instruction| bincode::serialized_size(instruction).is_ok_and(|size| size as usize == data.len()))
+}
+
+/// Names a system program instruction and lists its arguments in camelCase.
+fn describe_system_instruction(instruction: SystemInstruction) -> DecodedInstructionResponse {
+    let (name, fields) = match instruction {
+        SystemInstruction::CreateAccount { lamports, space, owner } => (
+            "CreateAccount",
+            json!({ "lamports": lamports, "space": space, "owner": owner.to_string() }),
+        ),
+        SystemInstruction::Assign { owner } => ("Assign", json!({ "owner": owner.to_string() })),
+        SystemInstruction::Transfer { lamports } => ("Transfer", json!({ "lamports": lamports })),
+        SystemInstruction::CreateAccountWithSeed { base, seed, lamports, space, owner } => (
+            "CreateAccountWithSeed",
+            json!({
+                "base": base.to_string(),
+                "seed": seed,
+                "lamports": lamports,
+                "space": space,
+                "owner": owner.to_string(),
+            }),
+        ),
+        SystemInstruction::AdvanceNonceAccount => ("AdvanceNonceAccount", json!({})),
+        SystemInstruction::WithdrawNonceAccount(lamports) => ("WithdrawNonceAccount", json!({ "lamports": lamports })),
+        SystemInstruction::InitializeNonceAccount(authority) => {
+            ("InitializeNonceAccount", json!({ "authority": authority.to_string() }))
+        }
+        SystemInstruction::AuthorizeNonceAccount(authority) => {
+            ("AuthorizeNonceAccount", json!({ "newAuthority": authority.to_string() }))
+        }
+        SystemInstruction::Allocate { space } => ("Allocate", json!({ "space": space })),
+        SystemInstruction::AllocateWithSeed { base, seed, space, owner } => (
+            "AllocateWithSeed",
+            json!({ "base": base.to_string(), "seed": seed, "space": space, "owner": owner.to_string() }),
+        ),
+        SystemInstruction::AssignWithSeed { base, seed, owner } => (
+            "AssignWithSeed",
+            json!({ "base": base.to_string(), "seed": seed, "owner": owner.to_string() }),
+        ),
+        SystemInstruction::TransferWithSeed { lamports, from_seed, from_owner } => (
+            "TransferWithSeed",
+            json!({ "lamports": lamports, "fromSeed": from_seed, "fromOwner": from_owner.to_string() }),
+        ),
+        SystemInstruction::UpgradeNonceAccount => ("UpgradeNonceAccount", json!({})),
+    };
+    DecodedInstructionResponse { instruction: name, fields }
+}
+
+/// Makes an RPC URL safe to log: credentials and query values are masked, as
+/// are long path segments, which providers commonly use for API keys.
+fn redact_url(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let (rest, query) = match rest.split_once('?') {
+        Some((rest, query)) => (rest, Some(query)),
+        None => (rest, None),
+    };
+    let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let host = match authority.rsplit_once('@') {
+        Some((_, host)) => format!("***@{}", host),
+        None => authority.to_string(),
+    };
+    let path: Vec<&str> = path
+        .split('/')
+        .map(|segment| if segment.len() >= 16 { "***" } else { segment })
+        .collect();
+    let mut redacted = if scheme.is_empty() { host } else { format!("{}://{}", scheme, host) };
+    if !path.iter().all(|segment| segment.is_empty()) {
+        redacted.push('/');
+        redacted.push_str(&path.join("/"));
+    }
+    if let Some(query) = query {
+        let query: Vec<String> = query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((key, _)) => format!("{}=***", key),
+                None => pair.to_string(),
+            })
+            .collect();
+        redacted.push('?');
+        redacted.push_str(&query.join("&"));
+    }
+    redacted
+}
+
+fn parse_commitment(value: &str) -> Option<CommitmentConfig> {
+    match value {
+        "processed" => Some(CommitmentConfig::processed()),
+        "confirmed" => Some(CommitmentConfig::confirmed()),
+        "finalized" => Some(CommitmentConfig::finalized()),
+        _ => None,
+    }
+}
+
+async fn health(State(state): State<AppState>) -> Json<SuccessResponse<HealthResponse>> {
+    Json(SuccessResponse {
+        success: true,
+        data: HealthResponse {
+            status: "ok",
+            read_only: state.read_only,
+        },
+    })
+}
+
+/// Bounds handling time by the tighter of `REQUEST_TIMEOUT_MS` and the
+/// client's `X-Request-Deadline` (unix millis). Dropping the handler future on
+/// expiry cancels any in-flight RPC call, and the client gets a JSON 504.
+async fn enforce_deadline(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let deadline = match req.headers().get("x-request-deadline") {
+        Some(value) => match value.to_str().ok().and_then(|v| v.parse::<u64>().ok()) {
+            Some(millis) => Some(millis),
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse::new("Invalid X-Request-Deadline; must be unix milliseconds")),
+                )
+                    .into_response()
+            }
+        },
+        None => None,
+    };
+    let remaining = deadline.map(|millis| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        Duration::from_millis(millis).saturating_sub(now)
+    });
+    let budget = match (remaining, state.request_timeout) {
+        (Some(remaining), Some(timeout)) => Some(remaining.min(timeout)),
+        (remaining, timeout) => remaining.or(timeout),
+    };
+
+    let deadline_exceeded = || {
+        (
+            StatusCode::GATEWAY_TIMEOUT,
+            Json(ErrorResponse::new("Request deadline exceeded").with_code("DEADLINE_EXCEEDED")),
+        )
+            .into_response()
+    };
+    match budget {
+        Some(budget) if budget.is_zero() => deadline_exceeded(),
+        Some(budget) => tokio::time::timeout(budget, next.run(req))
+            .await
+            .unwrap_or_else(|_| deadline_exceeded()),
+        None => next.run(req).await,
+    }
+}
+
+/// Longest client-supplied `X-Correlation-Id` we accept; longer or non-ASCII
+/// values are replaced with a fresh id.
+const MAX_CORRELATION_ID_LEN: usize = 128;
+
+/// Reads `X-Correlation-Id`, or generates a random UUID when it is absent,
+/// then runs the request inside a span carrying it so every log line is
+/// tagged. The id is echoed in the response header and in any
+/// `ErrorResponse` body.
+async fn correlation_id(req: Request, next: Next) -> Response {
+    let id: Box<str> = req
+        .headers()
+        .get("x-correlation-id")
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| {
+            !value.is_empty() && value.len() <= MAX_CORRELATION_ID_LEN && value.bytes().all(|b| b.is_ascii_graphic())
+        })
+        .map(Box::from)
+        .unwrap_or_else(|| random_uuid().into());
+    let span = tracing::info_span!("request", correlation_id = %id, method = %req.method(), path = %req.uri().path());
+    let mut response = CORRELATION_ID.scope(id.clone(), next.run(req).instrument(span)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert("x-correlation-id", value);
+    }
+    response
+}
+
+/// A random (version 4) UUID in its hyphenated form.
+fn random_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Largest body the HMAC check and the array guard will buffer, matching
+/// axum's default JSON limit.
+const MIDDLEWARE_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Requires `Authorization: HMAC-SHA256 t=<unix seconds>,sig=<hex>` where `sig`
+/// is HMAC-SHA256 over `"<t>.<body>"` keyed with `API_HMAC_SECRET`. Timestamps
+/// outside the allowed skew are rejected so captured requests cannot be
+/// replayed later. A no-op when no secret is configured.
+async fn require_hmac(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let Some(secret) = state.hmac_secret.clone() else {
+        return next.run(req).await;
+    };
+    let unauthorized = |msg: &str| {
+        (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse::new(msg).with_code("UNAUTHORIZED")),
+        )
+            .into_response()
+    };
+
+    let Some((timestamp, signature)) = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("HMAC-SHA256 "))
+        .and_then(|value| {
+            let (t, sig) = value.split_once(',')?;
+            let timestamp = t.trim().strip_prefix("t=")?.parse::<u64>().ok()?;
+            let signature = hex::decode(sig.trim().strip_prefix("sig=")?).ok()?;
+            Some((timestamp, signature))
+        })
+    else {
+        return unauthorized("Missing or malformed Authorization header");
+    };
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if now.abs_diff(timestamp) > state.hmac_max_skew.as_secs() {
+        return unauthorized("Authorization timestamp is outside the allowed window");
+    }
+
+    let (parts, body) = req.into_parts();
+    let body = match axum::body::to_bytes(body, MIDDLEWARE_BODY_LIMIT).await {
+        Ok(body) => body,
+        Err(_) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(ErrorResponse::new("Request body too large")),
+            )
+                .into_response()
+        }
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(&secret).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(&body);
+    if mac.verify_slice(&signature).is_err() {
+        return unauthorized("Invalid request signature");
+    }
+
+    next.run(Request::from_parts(parts, axum::body::Body::from(body))).await
+}
+
+/// Size of the longest array anywhere in a JSON document, found with a byte
+/// scan that allocates one stack slot per nesting level and nothing per element.
+/// Stops early once an array passes `limit`. Malformed JSON is left for the
+/// extractor to reject.
+fn longest_json_array(body: &[u8], limit: usize) -> usize {
+    // `Some(commas)` for an open array, `None` for an open object.
+    let mut open: Vec<Option<usize>> = Vec::new();
+    let mut longest = 0;
+    let (mut in_string, mut escaped) = (false, false);
+    for &byte in body {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' => open.push(Some(0)),
+            b'{' => open.push(None),
+            b']' | b'}' => {
+                open.pop();
+            }
+            b',' => {
+                if let Some(Some(commas)) = open.last_mut() {
+                    *commas += 1;
+                    longest = longest.max(*commas + 1);
+                    if longest > limit {
+                        return longest;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    longest
+}
+
+/// Turns away JSON bodies holding an array longer than the largest configured
+/// batch cap before any of it is deserialized, so an oversized list of pubkeys
+/// costs a byte scan rather than an allocation per element. Each endpoint still
+/// applies its own, possibly smaller, cap during validation. The bound never
+/// drops below `PACKET_DATA_SIZE`, so byte arrays such as a 64-byte secret or
+/// instruction data pass however low the batch caps are set.
+async fn limit_json_arrays(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if !is_json_content_type(req.headers()) {
+        return next.run(req).await;
+    }
+    let (parts, body) = req.into_parts();
+    let body = match axum::body::to_bytes(body, MIDDLEWARE_BODY_LIMIT).await {
+        Ok(body) => body,
+        Err(_) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(ErrorResponse::new("Request body too large")),
+            )
+                .into_response()
+        }
+    };
+    let limit = state.batch_limits.largest().max(PACKET_DATA_SIZE);
+    if longest_json_array(&body, limit) > limit {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(
+                ErrorResponse::new(&format!("Request contains an array of more than {} elements", limit))
+                    .with_code("ARRAY_TOO_LARGE"),
+            ),
+        )
+            .into_response();
+    }
+
+    next.run(Request::from_parts(parts, axum::body::Body::from(body))).await
+}
+
+/// Whether `name=true` (or `name=1`) appears in the query string.
+fn query_flag(uri: &Uri, name: &str) -> bool {
+    uri.query().is_some_and(|query| {
+        query.split('&').any(|pair| match pair.split_once('=') {
+            Some((key, value)) => key == name && (value == "true" || value == "1"),
+            None => false,
+        })
+    })
+}
+
+/// Re-serializes JSON responses with indentation when `?pretty=true` is set.
+async fn pretty_json(req: Request, next: Next) -> Response {
+    if !query_flag(req.uri(), "pretty") {
+        return next.run(req).await;
+    }
+    let response = next.run(req).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    // Transcoding keeps the original key order, which a `Value` round trip would sort.
+    let mut pretty = Vec::with_capacity(bytes.len() * 2);
+    let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+    let body = match serde_transcode::transcode(&mut deserializer, &mut serde_json::Serializer::pretty(&mut pretty)) {
+        Ok(()) => pretty,
+        Err(_) => bytes.to_vec(),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, axum::body::Body::from(body))
+}
+
+/// Whether the client listed `application/msgpack` (or `application/x-msgpack`)
+/// in its `Accept` header.
+fn accepts_msgpack(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|media_type| media_type.split(';').next().unwrap_or_default().trim())
+        .any(|media_type| {
+            media_type.eq_ignore_ascii_case("application/msgpack")
+                || media_type.eq_ignore_ascii_case("application/x-msgpack")
+        })
+}
+
+/// Re-encodes JSON responses, errors included, as MessagePack when the client
+/// sends `Accept: application/msgpack`. Runs outside `pretty_json`, so a
+/// `?pretty=true` on the same request has no visible effect.
+async fn msgpack_response(req: Request, next: Next) -> Response {
+    if !accepts_msgpack(req.headers()) {
+        return next.run(req).await;
+    }
+    let response = next.run(req).await;
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let mut packed = Vec::with_capacity(bytes.len());
+    let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+    if serde_transcode::transcode(&mut deserializer, &mut rmp_serde::Serializer::new(&mut packed)).is_err() {
+        return Response::from_parts(parts, axum::body::Body::from(bytes));
+    }
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/msgpack"));
+    Response::from_parts(parts, axum::body::Body::from(packed))
+}
+
+/// With `?errorsAs200=true`, reports error responses as 200 for clients that
+/// only read the body; the body already carries `success: false`, and the real
+/// status is kept in `X-Original-Status`.
+async fn errors_as_200(req: Request, next: Next) -> Response {
+    let enabled = query_flag(req.uri(), "errorsAs200");
+    let mut response = next.run(req).await;
+    let status = response.status();
+    if enabled && (status.is_client_error() || status.is_server_error()) {
+        response.headers_mut().insert("x-original-status", HeaderValue::from(status.as_u16()));
+        *response.status_mut() = StatusCode::OK;
+    }
+    response
+}
+
+/// Counts every request, matched or not, and its error class.
+async fn count_request(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let response = next.run(req).await;
+    state.stats.total.fetch_add(1, Ordering::Relaxed);
+    if response.status().is_client_error() {
+        state.stats.client_errors.fetch_add(1, Ordering::Relaxed);
+    } else if response.status().is_server_error() {
+        state.stats.server_errors.fetch_add(1, Ordering::Relaxed);
+    }
+    response
+}
+
+/// Counts requests per matched route; installed as a route layer so the
+/// matched path is known.
+async fn count_route(State(state): State<AppState>, path: MatchedPath, req: Request, next: Next) -> Response {
+    let response = next.run(req).await;
+    let is_error = response.status().is_client_error() || response.status().is_server_error();
+    state.stats.record_route(path.as_str(), is_error);
+    response
+}
+
+async fn stats(State(state): State<AppState>) -> Json<SuccessResponse<StatsResponse>> {
+    let routes = state
+        .stats
+        .routes
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(route, counters)| {
+            (
+                route.clone(),
+                RouteStatsResponse {
+                    requests: counters.requests.load(Ordering::Relaxed),
+                    errors: counters.errors.load(Ordering::Relaxed),
+                },
+            )
+        })
+        .collect();
+
+    Json(SuccessResponse {
+        success: true,
+        data: StatsResponse {
+            uptime_seconds: state.stats.started_at.elapsed().as_secs(),
+            total_requests: state.stats.total.load(Ordering::Relaxed),
+            client_errors: state.stats.client_errors.load(Ordering::Relaxed),
+            server_errors: state.stats.server_errors.load(Ordering::Relaxed),
+            routes,
+            caches: CacheStatsResponse {
+                mint_info: state.mint_cache.counters().await,
+                token_account_info: state.account_cache.counters().await,
+            },
+        },
+    })
+}
+
+async fn cluster_info(
+    State(state): State<AppState>,
+) -> Result<Json<SuccessResponse<ClusterInfoResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    // The lock only guards the snapshot; a slow or retrying RPC node must not
+    // stall every other poller, so the refresh happens without it.
+    let cached = state.cluster_info_cache.lock().await.clone();
+    if let Some((fetched_at, info)) = cached
+        && fetched_at.elapsed() < state.cluster_info_ttl
+    {
+        return Ok(Json(SuccessResponse {
+            success: true,
+            data: info,
+        }));
+    }
+
+    let rpc = rpc_client(&state)?;
+    let (slot, (blockhash, last_valid_block_height), epoch_info) = tokio::try_join!(
+        rpc.get_slot(),
+        rpc.get_latest_blockhash_with_commitment(rpc.commitment()),
+        rpc.get_epoch_info(),
+    )
+    .map_err(rpc_error)?;
+    let info = ClusterInfoResponse {
+        slot,
+        blockhash: blockhash.to_string(),
+        last_valid_block_height,
+        epoch_info,
+    };
+    *state.cluster_info_cache.lock().await = Some((Instant::now(), info.clone()));
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: info,
+    }))
+}
+
+/// Generates a keypair from the OS CSPRNG.
+///
+/// SECURITY: with `TEST_MODE` set, `entropyHex` makes the result fully
+/// determined by caller-supplied bytes. Anyone who knows or guesses that
+/// entropy has the secret key, so such keys must never hold real funds, and
+/// `TEST_MODE` must never be enabled in production. Without `TEST_MODE` the
+/// field is rejected.
+async fn generate_keypair(
+    State(state): State<AppState>,
+    req: Option<Json<GenerateKeypairRequest>>,
+) -> Result<Json<SuccessResponse<KeypairResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let entropy_hex = req.and_then(|Json(req)| req.entropy_hex);
+    let mut validation = Validation::default();
+    let seed = entropy_hex.as_deref().and_then(|entropy| {
+        if !state.test_mode {
+            validation.fail("entropyHex", "is only accepted in TEST_MODE", "entropyHex requires TEST_MODE");
+            return None;
+        }
+        let seed = hex::decode(entropy).ok().filter(|bytes| bytes.len() == 32);
+        if seed.is_none() {
+            validation.fail("entropyHex", "must be 64 hex characters", "Invalid entropyHex; must be 32 bytes of hex");
+        }
+        seed
+    });
+    validation.finish()?;
+
+    let keypair = match seed {
+        Some(seed) => keypair_from_seed(&seed).expect("32-byte seeds are always accepted"),
+        None => Keypair::new(),
+    };
+    let response = SuccessResponse {
+        success: true,
+        data: KeypairResponse {
+            pubkey: keypair.pubkey().to_string(),
+            secret: keypair.to_base58_string(),
+        },
+    };
+    Ok(Json(response))
+}
+
+/// Takes a permit for one piece of expensive blocking work, or answers 429 when
+/// all are in use. The permit travels into the blocking task, so it is held
+/// until the work finishes even if the client has gone.
+fn try_permit(
+    permits: &Arc<Semaphore>,
+    message: &str,
+    code: &'static str,
+) -> Result<OwnedSemaphorePermit, (StatusCode, Json<ErrorResponse>)> {
+    permits
+        .clone()
+        .try_acquire_owned()
+        .map_err(|_| (StatusCode::TOO_MANY_REQUESTS, Json(ErrorResponse::new(message).with_code(code))))
+}
+
+/// Raises its flag when dropped. A handler holds one so that blocking work it
+/// spawned stops once the request is abandoned, whether by the client
+/// disconnecting or by the request deadline.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Where a vanity pattern has to appear in the base58 public key.
+#[derive(Clone, Copy)]
+enum VanityMatch {
+    Prefix,
+    Suffix,
+    Contains,
+}
+
+impl VanityMatch {
+    fn matches(self, address: &str, pattern: &str) -> bool {
+        match self {
+            VanityMatch::Prefix => address.starts_with(pattern),
+            VanityMatch::Suffix => address.ends_with(pattern),
+            VanityMatch::Contains => address.contains(pattern),
+        }
+    }
+}
+
+/// Grinds random keypairs until the address matches `pattern`. Each base58
+/// character matches with probability about 1/58, so a prefix or suffix of n
+/// characters takes about 58^n attempts (≈195k for 3, ≈11M for 4); `contains`
+/// is roughly 40 times faster since the pattern may sit anywhere. Prefixes are
+/// capped at `VANITY_PREFIX_TIMEOUT` and the slower modes at the longer
+/// `VANITY_SUFFIX_TIMEOUT`; the grind runs on a blocking thread, at most
+/// `VANITY_CONCURRENCY` at a time, and stops early if the request is dropped.
+async fn generate_vanity_keypair(
+    State(state): State<AppState>,
+    Json(req): Json<VanityRequest>,
+) -> Result<Json<SuccessResponse<VanityResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if req.pattern.is_empty() || req.pattern.len() > MAX_VANITY_PATTERN_LEN {
+        validation.fail(
+            "pattern",
+            &format!("must be 1 to {} characters", MAX_VANITY_PATTERN_LEN),
+            "Invalid vanity pattern length",
+        );
+    } else if bs58::decode(&req.pattern).into_vec().is_err() {
+        validation.fail("pattern", "must only contain base58 characters", "Invalid vanity pattern; must be base58");
+    }
+    let mode = match req.match_mode.as_deref() {
+        None | Some("prefix") => VanityMatch::Prefix,
+        Some("suffix") => VanityMatch::Suffix,
+        Some("contains") => VanityMatch::Contains,
+        Some(_) => {
+            validation.fail("matchMode", "must be prefix, suffix or contains", "Invalid matchMode");
+            VanityMatch::Prefix
+        }
+    };
+    validation.finish()?;
+
+    let timeout = match mode {
+        VanityMatch::Prefix => VANITY_PREFIX_TIMEOUT,
+        VanityMatch::Suffix | VanityMatch::Contains => VANITY_SUFFIX_TIMEOUT,
+    };
+    let permit = try_permit(&state.vanity_permits, "Too many vanity searches in progress; retry shortly", "VANITY_BUSY")?;
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _cancel = CancelOnDrop(cancelled.clone());
+    let started = Instant::now();
+    let found = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        let mut attempts = 0u64;
+        while started.elapsed() < timeout && !cancelled.load(Ordering::Relaxed) {
+            attempts += 1;
+            let keypair = Keypair::new();
+            if mode.matches(&keypair.pubkey().to_string(), &req.pattern) {
+                return Some((keypair, attempts));
+            }
+        }
+        None
+    })
+    .await
+    .expect("vanity search does not panic");
+
+    let Some((keypair, attempts)) = found else {
+        return Err((
+            StatusCode::GATEWAY_TIMEOUT,
+            Json(
+                ErrorResponse::new(&format!("No matching address found within {}s", timeout.as_secs()))
+                    .with_code("VANITY_TIMEOUT"),
+            ),
+        ));
+    };
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: VanityResponse {
+            pubkey: keypair.pubkey().to_string(),
+            secret: keypair.to_base58_string(),
+            attempts,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        },
+    }))
+}
+
+/// Checks that `bytes` are a 64-byte keypair whose public half matches its
+/// secret half, recording a failure on `secret` otherwise.
+fn keypair_from_secret_bytes(validation: &mut Validation, bytes: &[u8]) -> Option<Keypair> {
+    if bytes.len() != 64 {
+        validation.fail("secret", "must decode to 64 bytes", "Invalid secret key length");
+        return None;
+    }
+    Keypair::try_from(bytes).ok().or_else(|| {
+        validation.fail(
+            "secret",
+            "public key does not match secret key",
+            "Invalid secret key; its public key does not match",
+        );
+        None
+    })
+}
+
+/// Checks that a base58 secret is a well-formed 64-byte keypair whose public
+/// half matches its secret half. Neither the secret nor any part of it is
+/// echoed back, including in errors.
+async fn inspect_keypair(
+    Json(req): Json<InspectKeypairRequest>,
+) -> Result<Json<SuccessResponse<InspectKeypairResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let keypair = match bs58::decode(&req.secret).into_vec() {
+        Err(_) => {
+            validation.fail("secret", "must be base58", "Invalid secret key format; must be base58");
+            None
+        }
+        Ok(bytes) => keypair_from_secret_bytes(&mut validation, &bytes),
+    };
+    validation.finish()?;
+    let keypair = keypair.expect("validated above");
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InspectKeypairResponse {
+            pubkey: keypair.pubkey().to_string(),
+        },
+    }))
+}
+
+/// The address for a secret, and nothing else: a smaller sibling of
+/// `/keypair/inspect` that also takes the JSON byte array `solana-keygen` writes.
+/// The secret is never echoed, including in errors.
+async fn keypair_pubkey(
+    Json(req): Json<KeypairPubkeyRequest>,
+) -> Result<Json<SuccessResponse<InspectKeypairResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let keypair = match req.secret {
+        SecretKeyInput::Base58(secret) => match bs58::decode(&secret).into_vec() {
+            Err(_) => {
+                validation.fail("secret", "must be base58", "Invalid secret key format; must be base58");
+                None
+            }
+            Ok(bytes) => keypair_from_secret_bytes(&mut validation, &bytes),
+        },
+        SecretKeyInput::Bytes(bytes) => keypair_from_secret_bytes(&mut validation, &bytes),
+    };
+    validation.finish()?;
+    let keypair = keypair.expect("validated above");
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InspectKeypairResponse {
+            pubkey: keypair.pubkey().to_string(),
+        },
+    }))
+}
+
+/// Generates a fresh keypair and signs `challenge` with it, for handshakes that
+/// only need proof of control over a new key. The signature verifies with
+/// `/message/verify`.
+async fn sign_challenge(
+    Json(req): Json<SignChallengeRequest>,
+) -> Result<Json<SuccessResponse<SignChallengeResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if req.challenge.is_empty() {
+        validation.fail("challenge", "is required", "Challenge must not be empty");
+    }
+    validation.finish()?;
+
+    let keypair = Keypair::new();
+    let signature = keypair.sign_message(req.challenge.as_bytes());
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: SignChallengeResponse {
+            pubkey: keypair.pubkey().to_string(),
+            signature: general_purpose::STANDARD.encode(signature.as_ref()),
+            secret: (!req.omit_secret).then(|| keypair.to_base58_string()),
+        },
+    }))
+}
+
+/// Derives the XChaCha20-Poly1305 key for `passphrase`. scrypt is deliberately
+/// slow (about 32 MiB and a noticeable fraction of a second), so callers run it
+/// on a blocking thread.
+fn passphrase_cipher(passphrase: &[u8], salt: &[u8]) -> XChaCha20Poly1305 {
+    let params = scrypt::Params::new(15, 8, 1, 32).expect("scrypt parameters are valid");
+    let mut key = [0u8; 32];
+    scrypt::scrypt(passphrase, salt, &params, &mut key).expect("32-byte output is valid");
+    <XChaCha20Poly1305 as chacha20poly1305::KeyInit>::new(&key.into())
+}
+
+/// Generates a keypair and returns its secret sealed under `passphrase`, so it
+/// can be stored without the server ever keeping it. The plaintext secret is
+/// not part of the response.
+async fn generate_encrypted_keypair(
+    State(state): State<AppState>,
+    Json(req): Json<EncryptKeypairRequest>,
+) -> Result<Json<SuccessResponse<EncryptedKeypairResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let distinct: std::collections::HashSet<char> = req.passphrase.chars().collect();
+    if req.passphrase.is_empty() {
+        validation.fail("passphrase", "is required", "Passphrase must not be empty");
+    } else if req.passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        validation.fail(
+            "passphrase",
+            &format!("must be at least {} characters", MIN_PASSPHRASE_LEN),
+            "Passphrase is too short",
+        );
+    } else if distinct.len() < MIN_PASSPHRASE_DISTINCT_CHARS {
+        validation.fail(
+            "passphrase",
+            &format!("must use at least {} different characters", MIN_PASSPHRASE_DISTINCT_CHARS),
+            "Passphrase is too weak",
+        );
+    }
+    validation.finish()?;
+
+    let permit = try_permit(
+        &state.scrypt_permits,
+        "Too many passphrase operations in progress; retry shortly",
+        "SCRYPT_BUSY",
+    )?;
+
+    let keypair = Keypair::new();
+    let salt: [u8; ENCRYPTED_SECRET_SALT_LEN] = rand::random();
+    let nonce: [u8; ENCRYPTED_SECRET_NONCE_LEN] = rand::random();
+    let plaintext = keypair.to_bytes();
+    let ciphertext = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        passphrase_cipher(req.passphrase.as_bytes(), &salt)
+            .encrypt(&nonce.into(), plaintext.as_slice())
+            .expect("encrypting 64 bytes cannot fail")
+    })
+    .await
+    .expect("encryption does not panic");
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: EncryptedKeypairResponse {
+            pubkey: keypair.pubkey().to_string(),
+            encrypted: EncryptedSecret {
+                version: ENCRYPTED_SECRET_VERSION,
+                salt: general_purpose::STANDARD.encode(salt),
+                nonce: general_purpose::STANDARD.encode(nonce),
+                ciphertext: general_purpose::STANDARD.encode(ciphertext),
+            },
+        },
+    }))
+}
+
+/// Opens a secret sealed by `/keypair/encrypted`. A wrong passphrase and a
+/// tampered envelope are indistinguishable by design and both fail the same way.
+async fn decrypt_keypair(
+    State(state): State<AppState>,
+    Json(req): Json<DecryptKeypairRequest>,
+) -> Result<Json<SuccessResponse<KeypairResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if req.passphrase.is_empty() {
+        validation.fail("passphrase", "is required", "Passphrase must not be empty");
+    }
+    if req.encrypted.version != ENCRYPTED_SECRET_VERSION {
+        validation.fail(
+            "encrypted.version",
+            &format!("must be {}", ENCRYPTED_SECRET_VERSION),
+            "Unsupported encryption version",
+        );
+    }
+    let mut decode = |field: &str, value: &str, len: Option<usize>| {
+        let bytes = general_purpose::STANDARD.decode(value).ok();
+        match (bytes, len) {
+            (None, _) => {
+                validation.fail(field, "must be base64", &format!("Invalid {}; must be base64", field));
+                None
+            }
+            (Some(bytes), Some(len)) if bytes.len() != len => {
+                validation.fail(field, &format!("must decode to {} bytes", len), &format!("Invalid {} length", field));
+                None
+            }
+            (Some(bytes), _) => Some(bytes),
+        }
+    };
+    let salt = decode("encrypted.salt", &req.encrypted.salt, Some(ENCRYPTED_SECRET_SALT_LEN));
+    let nonce = decode("encrypted.nonce", &req.encrypted.nonce, Some(ENCRYPTED_SECRET_NONCE_LEN));
+    let ciphertext = decode("encrypted.ciphertext", &req.encrypted.ciphertext, None);
+    validation.finish()?;
+    let (salt, nonce, ciphertext) = (
+        salt.expect("validated above"),
+        nonce.expect("validated above"),
+        ciphertext.expect("validated above"),
+    );
+    let permit = try_permit(
+        &state.scrypt_permits,
+        "Too many passphrase operations in progress; retry shortly",
+        "SCRYPT_BUSY",
+    )?;
+
+    let plaintext = tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        passphrase_cipher(req.passphrase.as_bytes(), &salt)
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .ok()
+    })
+    .await
+    .expect("decryption does not panic");
+    let keypair = plaintext.and_then(|bytes| Keypair::try_from(bytes.as_slice()).ok()).ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("Wrong passphrase or corrupted ciphertext")),
+        )
+    })?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: KeypairResponse {
+            pubkey: keypair.pubkey().to_string(),
+            secret: keypair.to_base58_string(),
+        },
+    }))
+}
+
+/// Tries base58, hex and a JSON byte array (as written by `solana-keygen`) and
+/// reports which decode and what the bytes look like. Like `/keypair/inspect`,
+/// the secret itself is never echoed.
+async fn detect_key_format(
+    Json(req): Json<DetectKeyFormatRequest>,
+) -> Result<Json<SuccessResponse<DetectKeyFormatResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let value = req.value.trim();
+    let decoded = [
+        ("base58", bs58::decode(value).into_vec().ok()),
+        ("hex", hex::decode(value.strip_prefix("0x").unwrap_or(value)).ok()),
+        ("jsonArray", serde_json::from_str::<Vec<u8>>(value).ok()),
+    ];
+    let formats = decoded
+        .into_iter()
+        .filter_map(|(format, bytes)| {
+            let bytes = bytes.filter(|bytes| !bytes.is_empty())?;
+            let (kind, pubkey) = match bytes.len() {
+                64 => match Keypair::try_from(bytes.as_slice()) {
+                    Ok(keypair) => ("keypair", Some(keypair.pubkey().to_string())),
+                    Err(_) => ("mismatchedKeypair", None),
+                },
+                32 => ("seed", None),
+                _ => ("unknown", None),
+            };
+            Some(DetectedKeyFormat {
+                format,
+                bytes: bytes.len(),
+                kind,
+                pubkey,
+            })
+        })
+        .collect();
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: DetectKeyFormatResponse { formats },
+    }))
+}
+
+/// Derives a random program address, which by construction lies off the
+/// ed25519 curve. There is no secret key: nothing can sign for this address,
+/// so it is only useful for exercising PDA code paths.
+async fn generate_off_curve_address(
+    req: Option<Json<OffCurveRequest>>,
+) -> Result<Json<SuccessResponse<OffCurveResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let program_id = validation
+        .optional_pubkey(
+            "programId",
+            req.as_ref().and_then(|Json(req)| req.program_id.as_deref()),
+            "Invalid program id",
+        )
+        .unwrap_or(solana_system_interface::program::ID);
+    validation.finish()?;
+
+    // `find_program_address` walks bumps down from 255 until the hash is off-curve.
+    let seed: [u8; 32] = rand::random();
+    let (address, bump) = Pubkey::find_program_address(&[&seed], &program_id);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: OffCurveResponse {
+            address: address.to_string(),
+            program_id: program_id.to_string(),
+            seed: hex::encode(seed),
+            bump,
+        },
+    }))
+}
+
+/// Derives `count` keypairs from a BIP39 mnemonic (no passphrase), one per
+/// index substituted into the path template.
+async fn generate_hd_batch(
+    State(state): State<AppState>,
+    Json(req): Json<HdBatchRequest>,
+) -> Result<Json<SuccessResponse<HdBatchResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let mnemonic = bip39::Mnemonic::parse_normalized(&req.mnemonic).ok().or_else(|| {
+        validation.fail("mnemonic", "invalid BIP39 mnemonic", "Invalid mnemonic");
+        None
+    });
+    if req.count == 0 {
+        validation.fail("count", "must be at least 1", "Invalid keypair count");
+    }
+    validation.batch_limit("count", req.count as usize, state.batch_limits.keypairs, "keypairs");
+    // Every path component is hardened, which leaves 31 bits for the index.
+    if req.start_index.checked_add(req.count).is_none_or(|end| end > 1 << 31) {
+        validation.fail("startIndex", "index range exceeds 2^31", "Derivation index out of range");
+    }
+    let template = req.path_template.as_deref().unwrap_or(DEFAULT_HD_PATH_TEMPLATE);
+    if template.matches("{i}").count() != 1
+        || DerivationPath::from_absolute_path_str(&template.replace("{i}", "0")).is_err()
+    {
+        validation.fail(
+            "pathTemplate",
+            "must be a derivation path containing {i} once",
+            "Invalid derivation path template",
+        );
+    }
+    validation.finish()?;
+
+    let seed = mnemonic.expect("validated above").to_seed("");
+    let keypairs = (req.start_index..req.start_index + req.count)
+        .map(|index| {
+            let path = template.replace("{i}", &index.to_string());
+            let derivation_path = DerivationPath::from_absolute_path_str(&path).ok();
+            let keypair = keypair_from_seed_and_derivation_path(&seed, derivation_path).map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse::new(&format!("Failed to derive keypair at {}: {}", path, e))),
+                )
+            })?;
+            Ok(HdKeypair {
+                index,
+                path,
+                pubkey: keypair.pubkey().to_string(),
+                secret: keypair.to_base58_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: HdBatchResponse { keypairs },
+    }))
+}
+
+/// Parses a BIP32 path such as `m/44'/501'/0'/0'`. Hardened segments may be
+/// marked with `'`, `h` or `H`; the normalized form always uses `'`.
+fn parse_derivation_path(path: &str) -> Result<Vec<DerivationComponent>, String> {
+    let mut segments = path.trim().split('/');
+    if !matches!(segments.next(), Some("m" | "M")) {
+        return Err("must start with m".to_string());
+    }
+    let components = segments
+        .enumerate()
+        .map(|(position, segment)| {
+            let (digits, hardened) = match segment.strip_suffix(['\'', 'h', 'H']) {
+                Some(digits) => (digits, true),
+                None => (segment, false),
+            };
+            digits
+                .parse::<u32>()
+                .ok()
+                .filter(|index| !digits.starts_with('+') && *index < 1 << 31)
+                .map(|index| DerivationComponent { index, hardened })
+                .ok_or_else(|| {
+                    format!("segment {} ({:?}) must be an index below 2^31, optionally followed by '", position + 1, segment)
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if components.len() > u8::MAX as usize {
+        return Err(format!("must have at most {} segments", u8::MAX));
+    }
+    Ok(components)
+}
+
+/// Reports whether `path` is usable for Solana key derivation. Malformed paths
+/// come back as `valid: false` with a reason rather than as an error, so the
+/// result can be shown next to the input as the user types.
+async fn validate_derivation_path(
+    Json(req): Json<DerivationValidateRequest>,
+) -> Result<Json<SuccessResponse<DerivationValidateResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let data = match parse_derivation_path(&req.path) {
+        Err(reason) => DerivationValidateResponse {
+            valid: false,
+            normalized: None,
+            components: None,
+            reason: Some(reason),
+        },
+        Ok(components) => {
+            let normalized = std::iter::once("m".to_string())
+                .chain(components.iter().map(|c| format!("{}{}", c.index, if c.hardened { "'" } else { "" })))
+                .collect::<Vec<_>>()
+                .join("/");
+            // ed25519 (SLIP-0010) has no public derivation, so every segment
+            // must be hardened.
+            let reason = components.iter().position(|c| !c.hardened).map(|position| {
+                format!("segment {} is not hardened; Solana derivation only supports hardened indexes", position + 1)
+            });
+            DerivationValidateResponse {
+                valid: reason.is_none(),
+                normalized: Some(normalized),
+                components: Some(components),
+                reason,
+            }
+        }
+    };
+    Ok(Json(SuccessResponse { success: true, data }))
+}
+
+/// Generates a keypair and the `create_account` instruction that funds it from
+/// `payer`. Both the payer and the new keypair must sign the transaction.
+async fn generate_keypair_with_create_account(
+    Json(req): Json<KeypairWithCreateAccountRequest>,
+) -> Result<Json<SuccessResponse<KeypairWithCreateAccountResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let payer_pubkey = validation.pubkey("payer", &req.payer, "Invalid payer public key");
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    if req.lamports == 0 {
+        validation.fail("lamports", "must be greater than 0", "Cannot fund an account with 0 lamports");
+    }
+    if req.space > MAX_PERMITTED_DATA_LENGTH {
+        validation.fail(
+            "space",
+            &format!("must be at most {}", MAX_PERMITTED_DATA_LENGTH),
+            "Account space exceeds the maximum permitted data length",
+        );
+    }
+    validation.finish()?;
+
+    let keypair = Keypair::new();
+    let instruction = system_instruction::create_account(
+        &payer_pubkey,
+        &keypair.pubkey(),
+        req.lamports,
+        req.space,
+        &owner_pubkey,
+    );
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: KeypairWithCreateAccountResponse {
+            pubkey: keypair.pubkey().to_string(),
+            secret: keypair.to_base58_string(),
+            instruction: instruction.into(),
+        },
+    }))
+}
+
+/// Everything to create a classic SPL mint and seed its first holder: the
+/// payer, the new mint keypair and the mint authority all sign.
+async fn launch_token(
+    State(state): State<AppState>,
+    Json(req): Json<TokenLaunchRequest>,
+) -> Result<Json<SuccessResponse<TokenLaunchResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let payer_pubkey = validation.pubkey("payer", &req.payer, "Invalid payer public key");
+    let mint_authority_pubkey =
+        validation.pubkey("mintAuthority", &req.mint_authority, "Invalid mint authority public key");
+    let recipient_pubkey = validation.pubkey("recipient", &req.recipient, "Invalid recipient public key");
+    if req.decimals > MAX_TOKEN_DECIMALS {
+        validation.fail(
+            "decimals",
+            &format!("must be at most {}", MAX_TOKEN_DECIMALS),
+            "Decimals cannot exceed 9",
+        );
+    }
+    validation.finish()?;
+
+    let space = spl_token::state::Mint::LEN;
+    let rent_lamports = match state.rpc.as_deref() {
+        Some(rpc) => rpc.get_minimum_balance_for_rent_exemption(space).await.map_err(rpc_error)?,
+        None => Rent::default().minimum_balance(space),
+    };
+
+    let mint = Keypair::new();
+    let mint_pubkey = mint.pubkey();
+    let ata = get_associated_token_address(&recipient_pubkey, &mint_pubkey);
+    let mut instructions = vec![
+        system_instruction::create_account(&payer_pubkey, &mint_pubkey, rent_lamports, space as u64, &spl_token::ID),
+        spl_token::instruction::initialize_mint(&spl_token::ID, &mint_pubkey, &mint_authority_pubkey, None, req.decimals)
+            .map_err(instruction_error)?,
+        create_associated_token_account_idempotent(&payer_pubkey, &recipient_pubkey, &mint_pubkey, &spl_token::ID),
+    ];
+    if req.initial_supply > 0 {
+        instructions.push(
+            spl_token::instruction::mint_to(
+                &spl_token::ID,
+                &mint_pubkey,
+                &ata,
+                &mint_authority_pubkey,
+                &[],
+                req.initial_supply,
+            )
+            .map_err(instruction_error)?,
+        );
+    }
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: TokenLaunchResponse {
+            mint: mint_pubkey.to_string(),
+            mint_secret: mint.to_base58_string(),
+            ata: ata.to_string(),
+            rent_lamports,
+            instructions: instructions.into_iter().map(SerializableInstruction::from).collect(),
+        },
+    }))
+}
+
+fn build_create_token(
+    req: &CreateTokenRequest,
+    default_decimals: u8,
+) -> Result<MintSetup, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let decimals = req.decimals.unwrap_or(default_decimals);
+    if decimals > MAX_TOKEN_DECIMALS {
+        validation.fail(
+            "decimals",
+            &format!("must be at most {}", MAX_TOKEN_DECIMALS),
+            "Decimals cannot exceed 9",
+        );
+    }
+    let use_mint2 = match req.version {
+        None | Some(1) => false,
+        Some(2) => true,
+        Some(_) => {
+            validation.fail("version", "must be 1 or 2", "Invalid initialize_mint version; must be 1 or 2");
+            false
+        }
+    };
+    let mint_authority_pubkey =
+        validation.pubkey("mintAuthority", &req.mint_authority, "Invalid mint authority public key");
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let program_id = validation.token_program("program", req.program.as_deref());
+    let metadata_pointer = req.metadata_pointer.as_ref().map(|config| {
+        // The program rejects a pointer with neither an authority nor an address.
+        if config.authority.is_none() && config.metadata_address.is_none() {
+            validation.fail(
+                "metadataPointer",
+                "must set authority, metadataAddress or both",
+                "Metadata pointer needs an authority or a metadata address",
+            );
+        }
+        (
+            validation.optional_pubkey(
+                "metadataPointer.authority",
+                config.authority.as_deref(),
+                "Invalid metadata pointer authority public key",
+            ),
+            validation.optional_pubkey(
+                "metadataPointer.metadataAddress",
+                config.metadata_address.as_deref(),
+                "Invalid metadata address public key",
+            ),
+        )
+    });
+    let transfer_fee = req.transfer_fee.as_ref().map(|config| {
+        if config.transfer_fee_basis_points > MAX_FEE_BASIS_POINTS {
+            validation.fail(
+                "transferFee.transferFeeBasisPoints",
+                &format!("must be at most {}", MAX_FEE_BASIS_POINTS),
+                "Transfer fee basis points cannot exceed 10000",
+            );
+        }
+        (
+            validation.optional_pubkey(
+                "transferFee.transferFeeConfigAuthority",
+                config.transfer_fee_config_authority.as_deref(),
+                "Invalid transfer fee config authority public key",
+            ),
+            validation.optional_pubkey(
+                "transferFee.withdrawWithheldAuthority",
+                config.withdraw_withheld_authority.as_deref(),
+                "Invalid withdraw withheld authority public key",
+            ),
+            config.transfer_fee_basis_points,
+            config.maximum_fee,
+        )
+    });
+    let close_authority =
+        validation.optional_pubkey("closeAuthority", req.close_authority.as_deref(), "Invalid close authority public key");
+    let freeze_authority = validation.optional_pubkey(
+        "freezeAuthority",
+        req.freeze_authority.as_deref(),
+        "Invalid freeze authority public key",
+    );
+    let default_account_state = match req.default_account_state.as_deref() {
+        None => None,
+        Some("initialized") => Some(spl_token_2022::state::AccountState::Initialized),
+        Some("frozen") => {
+            if req.freeze_authority.is_none() {
+                validation.fail(
+                    "freezeAuthority",
+                    "is required when defaultAccountState is frozen",
+                    "Frozen default account state requires a freeze authority",
+                );
+            }
+            Some(spl_token_2022::state::AccountState::Frozen)
+        }
+        Some(_) => {
+            validation.fail(
+                "defaultAccountState",
+                "must be frozen or initialized",
+                "Invalid default account state; must be frozen or initialized",
+            );
+            None
+        }
+    };
+    if program_id != spl_token_2022::ID {
+        let requested = [
+            ("metadataPointer", metadata_pointer.is_some()),
+            ("transferFee", transfer_fee.is_some()),
+            ("closeAuthority", close_authority.is_some()),
+            ("defaultAccountState", default_account_state.is_some()),
+        ];
+        for (field, present) in requested {
+            if present {
+                validation.fail(field, "requires program token2022", "Mint extensions require the token2022 program");
+            }
+        }
+    }
+    validation.finish()?;
+
+    if program_id == spl_token::ID {
+        let initialize_mint = if use_mint2 {
+            spl_token::instruction::initialize_mint2
+        } else {
+            spl_token::instruction::initialize_mint
+        };
+        let instruction = initialize_mint(
+            &spl_token::ID,
+            &mint_pubkey,
+            &mint_authority_pubkey,
+            freeze_authority.as_ref(),
+            decimals,
+        )
+        .map_err(instruction_error)?;
+        return Ok(MintSetup {
+            instructions: vec![instruction],
+            space: spl_token::state::Mint::LEN,
+            decimals,
+            builder: if use_mint2 {
+                "spl_token::initialize_mint2"
+            } else {
+                "spl_token::initialize_mint"
+            },
+        });
+    }
+
+    // Extension initializers must run before `initialize_mint`.
+    let mut extensions = Vec::new();
+    let mut instructions = Vec::new();
+    if let Some((authority, metadata_address)) = metadata_pointer {
+        extensions.push(ExtensionType::MetadataPointer);
+        instructions.push(
+            spl_token_2022::extension::metadata_pointer::instruction::initialize(
+                &spl_token_2022::ID,
+                &mint_pubkey,
+                authority,
+                metadata_address,
+            )
+            .map_err(instruction_error)?,
+        );
+    }
+    if let Some((config_authority, withdraw_authority, basis_points, maximum_fee)) = transfer_fee {
+        extensions.push(ExtensionType::TransferFeeConfig);
+        instructions.push(
+            spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config(
+                &spl_token_2022::ID,
+                &mint_pubkey,
+                config_authority.as_ref(),
+                withdraw_authority.as_ref(),
+                basis_points,
+                maximum_fee,
+            )
+            .map_err(instruction_error)?,
+        );
+    }
+    if let Some(close_authority) = close_authority {
+        extensions.push(ExtensionType::MintCloseAuthority);
+        instructions.push(
+            spl_token_2022::instruction::initialize_mint_close_authority(
+                &spl_token_2022::ID,
+                &mint_pubkey,
+                Some(&close_authority),
+            )
+            .map_err(instruction_error)?,
+        );
+    }
+    if let Some(state) = default_account_state {
+        extensions.push(ExtensionType::DefaultAccountState);
+        instructions.push(
+            spl_token_2022::extension::default_account_state::instruction::initialize_default_account_state(
+                &spl_token_2022::ID,
+                &mint_pubkey,
+                &state,
+            )
+            .map_err(instruction_error)?,
+        );
+    }
+    let initialize_mint = if use_mint2 {
+        spl_token_2022::instruction::initialize_mint2
+    } else {
+        spl_token_2022::instruction::initialize_mint
+    };
+    instructions.push(
+        initialize_mint(
+            &spl_token_2022::ID,
+            &mint_pubkey,
+            &mint_authority_pubkey,
+            freeze_authority.as_ref(),
+            decimals,
+        )
+        .map_err(instruction_error)?,
+    );
+    let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&extensions)
+        .map_err(instruction_error)?;
+
+    Ok(MintSetup {
+        instructions,
+        space,
+        decimals,
+        builder: if use_mint2 {
+            "spl_token_2022::initialize_mint2"
+        } else {
+            "spl_token_2022::initialize_mint"
+        },
+    })
+}
+
+async fn create_token(
+    State(state): State<AppState>,
+    uri: Uri,
+    Json(req): Json<CreateTokenRequest>,
+) -> Result<Json<SuccessResponse<CreateTokenResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let setup = build_create_token(&req, state.default_decimals)?;
+    let is_token_2022 = req.program.as_deref() == Some("token2022");
+    let initialize_mint = setup
+        .instructions
+        .last()
+        .cloned()
+        .expect("mint setup always ends with initialize_mint");
+    let meta = InstructionMeta::requested(&uri, setup.builder, &initialize_mint);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: CreateTokenResponse {
+            instruction: SerializableInstruction::expanded(initialize_mint, req.include_program_accounts),
+            decimals: setup.decimals,
+            example: DecimalsExample {
+                raw_per_whole_token: 10u64.checked_pow(setup.decimals as u32),
+            },
+            instructions: is_token_2022.then(|| {
+                setup
+                    .instructions
+                    .iter()
+                    .cloned()
+                    .map(|ix| SerializableInstruction::expanded(ix, req.include_program_accounts))
+                    .collect()
+            }),
+            space: is_token_2022.then_some(setup.space),
+            compute_unit_hint: req
+                .include_compute_hint
+                .then(|| ComputeUnitHint::for_instructions(&setup.instructions)),
+            meta,
+        },
+    }))
+}
+
+fn build_mint_to(req: &MintTokenRequest) -> Result<Instruction, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let destination_pubkey = validation.pubkey("destination", &req.destination, "Invalid destination public key");
+    let authority_pubkey = validation.pubkey("authority", &req.authority, "Invalid authority public key");
+    validation.finish()?;
+
+    spl_token::instruction::mint_to(
+        &spl_token::ID,
+        &mint_pubkey,
+        &destination_pubkey,
+        &authority_pubkey,
+        &[],
+        req.amount,
+    )
+    .map_err(instruction_error)
+}
+
+/// One `mint_to` per recipient from a single authority, each optionally
+/// preceded by an idempotent create of the recipient's ATA.
+async fn mint_batch(
+    State(state): State<AppState>,
+    Json(req): Json<MintBatchRequest>,
+) -> Result<Json<SuccessResponse<InstructionListResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let authority_pubkey = validation.pubkey("authority", &req.authority, "Invalid authority public key");
+    if req.recipients.is_empty() {
+        validation.fail("recipients", "must not be empty", "At least one recipient is required");
+    }
+    if req.resolve_ata && req.recipients.len() > MAX_MINT_BATCH_WITH_ATA {
+        validation.fail(
+            "recipients",
+            &format!("must contain at most {} recipients with resolveAta", MAX_MINT_BATCH_WITH_ATA),
+            "Too many recipients for one transaction",
+        );
+    }
+    validation.batch_limit("recipients", req.recipients.len(), state.batch_limits.mints, "recipients");
+    let recipients: Vec<(Pubkey, u64)> = req
+        .recipients
+        .iter()
+        .enumerate()
+        .map(|(i, recipient)| {
+            let destination = validation.pubkey(
+                &format!("recipients[{}].destination", i),
+                &recipient.destination,
+                "Invalid destination public key",
+            );
+            if recipient.amount == 0 {
+                validation.fail(
+                    &format!("recipients[{}].amount", i),
+                    "must be greater than 0",
+                    "Cannot mint 0 tokens",
+                );
+            }
+            (destination, recipient.amount)
+        })
+        .collect();
+    validation.finish()?;
+
+    let mut instructions = Vec::new();
+    for (destination, amount) in recipients {
+        let token_account = if req.resolve_ata {
+            instructions.push(create_associated_token_account_idempotent(
+                &authority_pubkey,
+                &destination,
+                &mint_pubkey,
+                &spl_token::ID,
+            ));
+            get_associated_token_address(&destination, &mint_pubkey)
+        } else {
+            destination
+        };
+        instructions.push(
+            spl_token::instruction::mint_to(&spl_token::ID, &mint_pubkey, &token_account, &authority_pubkey, &[], amount)
+                .map_err(instruction_error)?,
+        );
+    }
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InstructionListResponse {
+            instructions: instructions.into_iter().map(SerializableInstruction::from).collect(),
+        },
+    }))
+}
+
+async fn mint_token(
+    uri: Uri,
+    Json(req): Json<MintTokenRequest>,
+) -> Result<Json<SuccessResponse<InstructionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let instruction = build_mint_to(&req)?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InstructionResponse {
+            compute_unit_hint: req
+                .include_compute_hint
+                .then(|| ComputeUnitHint::for_instructions([&instruction])),
+            meta: InstructionMeta::requested(&uri, "spl_token::mint_to", &instruction),
+            instruction: SerializableInstruction::expanded(instruction, req.include_program_accounts),
+        },
+    }))
+}
+
+/// `set_authority(MintTokens, None)`, which fixes the supply for good: once it
+/// lands nobody can mint again.
+async fn disable_mint_authority(
+    uri: Uri,
+    Json(req): Json<DisableMintAuthorityRequest>,
+) -> Result<Json<SuccessResponse<InstructionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let authority_pubkey =
+        validation.pubkey("currentAuthority", &req.current_authority, "Invalid current authority public key");
+    let program_id = validation.token_program("program", req.program.as_deref());
+    validation.finish()?;
+
+    let instruction = spl_token_2022::instruction::set_authority(
+        &program_id,
+        &mint_pubkey,
+        None,
+        spl_token_2022::instruction::AuthorityType::MintTokens,
+        &authority_pubkey,
+        &[],
+    )
+    .map_err(instruction_error)?;
+    tracing::info!(mint = %mint_pubkey, authority = %authority_pubkey, "built mint authority revocation");
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InstructionResponse {
+            compute_unit_hint: None,
+            meta: InstructionMeta::requested(
+                &uri,
+                if program_id == spl_token_2022::ID {
+                    "spl_token_2022::set_authority"
+                } else {
+                    "spl_token::set_authority"
+                },
+                &instruction,
+            ),
+            instruction: instruction.into(),
+        },
+    }))
+}
+
+async fn sign_message(
+    Json(req): Json<SignMessageRequest>,
+) -> Result<Json<SuccessResponse<SignMessageResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if req.message.is_empty() {
+        validation.fail("message", "is required", "Missing required fields");
+    }
+    if req.secret.is_empty() {
+        validation.fail("secret", "is required", "Missing required fields");
+    }
+    validation.finish()?;
+
+    let keypair = decode_keypair(&req.secret)?;
+
+    let signature = keypair.sign_message(req.message.as_bytes());
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: SignMessageResponse {
+            signature: general_purpose::STANDARD.encode(signature.as_ref()),
+            public_key: keypair.pubkey().to_string(),
+            message: req.message,
+        },
+    }))
+}
+
+/// Prefix of every permit payload; also keeps a permit signature from being
+/// valid for any other kind of message.
+const PERMIT_MAGIC: &[u8] = b"solana-permit:v1";
+
+impl Permit {
+    /// The canonical bytes a permit signature covers: `PERMIT_MAGIC`, then
+    /// `domain` as a u32 little-endian byte length followed by its UTF-8
+    /// bytes, `nonce` and `expiry` as u64 little-endian, and `action` as
+    /// another length-prefixed string. Length prefixes keep the encoding
+    /// unambiguous whatever the strings contain.
+    fn payload(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(PERMIT_MAGIC.len() + 24 + self.domain.len() + self.action.len());
+        payload.extend_from_slice(PERMIT_MAGIC);
+        payload.extend_from_slice(&(self.domain.len() as u32).to_le_bytes());
+        payload.extend_from_slice(self.domain.as_bytes());
+        payload.extend_from_slice(&self.nonce.to_le_bytes());
+        payload.extend_from_slice(&self.expiry.to_le_bytes());
+        payload.extend_from_slice(&(self.action.len() as u32).to_le_bytes());
+        payload.extend_from_slice(self.action.as_bytes());
+        payload
+    }
+
+    /// Records a failure for each empty string field.
+    fn validate(&self, validation: &mut Validation) {
+        if self.domain.is_empty() {
+            validation.fail("domain", "is required", "Missing required fields");
+        }
+        if self.action.is_empty() {
+            validation.fail("action", "is required", "Missing required fields");
+        }
+    }
+}
+
+/// Signs a permit's canonical payload (see `Permit::payload`).
+async fn sign_permit(
+    Json(req): Json<SignPermitRequest>,
+) -> Result<Json<SuccessResponse<SignPermitResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    req.permit.validate(&mut validation);
+    if req.secret.is_empty() {
+        validation.fail("secret", "is required", "Missing required fields");
+    }
+    validation.finish()?;
+
+    let keypair = decode_keypair(&req.secret)?;
+    let payload = req.permit.payload();
+    let signature = keypair.sign_message(&payload);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: SignPermitResponse {
+            signature: general_purpose::STANDARD.encode(signature.as_ref()),
+            payload: general_purpose::STANDARD.encode(&payload),
+            pubkey: keypair.pubkey().to_string(),
+        },
+    }))
+}
+
+/// Re-canonicalizes the permit and checks its signature and expiry. An expired
+/// permit is never valid, however good its signature.
+async fn verify_permit(
+    Json(req): Json<VerifyPermitRequest>,
+) -> Result<Json<SuccessResponse<VerifyPermitResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    req.permit.validate(&mut validation);
+    let pubkey = validation.pubkey("pubkey", &req.pubkey, "Invalid public key");
+    let signature = validation.signature("signature", &req.signature);
+    validation.finish()?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let expired = now > req.permit.expiry;
+    let signed = signature.verify(pubkey.as_ref(), &req.permit.payload());
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: VerifyPermitResponse {
+            valid: signed && !expired,
+            expired,
+        },
+    }))
+}
+
+/// Signs `message` wrapped in the Solana off-chain message envelope (version
+/// 0, `\xffsolana offchain` signing domain) rather than the raw bytes, so the
+/// signature matches what `solana sign-offchain-message` and wallets produce.
+async fn sign_message_offchain(
+    Json(req): Json<SignMessageRequest>,
+) -> Result<Json<SuccessResponse<SignOffchainResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if req.message.is_empty() {
+        validation.fail("message", "is required", "Missing required fields");
+    }
+    if req.secret.is_empty() {
+        validation.fail("secret", "is required", "Missing required fields");
+    }
+    validation.finish()?;
+
+    let keypair = decode_keypair(&req.secret)?;
+    let too_long = |_| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("Message is too long for an off-chain message")),
+        )
+    };
+    let message = OffchainMessage::new(0, req.message.as_bytes()).map_err(too_long)?;
+    let envelope = message.serialize().map_err(too_long)?;
+    let signature = keypair.sign_message(&envelope);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: SignOffchainResponse {
+            signature: general_purpose::STANDARD.encode(signature.as_ref()),
+            public_key: keypair.pubkey().to_string(),
+            envelope: general_purpose::STANDARD.encode(&envelope),
+            format: match message.get_format() {
+                MessageFormat::RestrictedAscii => "restrictedAscii",
+                MessageFormat::LimitedUtf8 => "limitedUtf8",
+                MessageFormat::ExtendedUtf8 => "extendedUtf8",
+            },
+        },
+    }))
+}
+
+async fn hash_message(
+    Json(req): Json<HashMessageRequest>,
+) -> Result<Json<SuccessResponse<HashMessageResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if !matches!(req.algorithm.as_str(), "sha256" | "keccak256") {
+        validation.fail(
+            "algorithm",
+            "must be sha256 or keccak256",
+            "Invalid hash algorithm; must be sha256 or keccak256",
+        );
+    }
+    validation.finish()?;
+
+    let digest = if req.algorithm == "sha256" {
+        Sha256::digest(req.message.as_bytes()).to_vec()
+    } else {
+        Keccak256::digest(req.message.as_bytes()).to_vec()
+    };
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: HashMessageResponse {
+            algorithm: req.algorithm,
+            digest: hex::encode(digest),
+        },
+    }))
+}
+
+async fn verify_message(
+    Json(req): Json<VerifyMessageRequest>,
+) -> Result<Json<SuccessResponse<VerifyMessageResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let pubkey = validation.pubkey("pubkey", &req.pubkey, "Invalid public key");
+    let signature = validation.signature("signature", &req.signature);
+    validation.finish()?;
+
+    let valid = signature.verify(pubkey.as_ref(), req.message.as_bytes());
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: VerifyMessageResponse {
+            valid,
+            message: req.message,
+            pubkey: req.pubkey,
+        },
+    }))
+}
+
+/// Checks the signature against each listed pubkey, stopping at the first match.
+async fn verify_message_any(
+    Json(req): Json<VerifyAnyRequest>,
+) -> Result<Json<SuccessResponse<VerifyAnyResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let signature = validation.signature("signature", &req.signature);
+    if req.pubkeys.is_empty() {
+        validation.fail("pubkeys", "must not be empty", "At least one public key is required");
+    }
+    let pubkeys: Vec<Pubkey> = req
+        .pubkeys
+        .iter()
+        .enumerate()
+        .map(|(i, pubkey)| validation.pubkey(&format!("pubkeys[{}]", i), pubkey, "Invalid public key"))
+        .collect();
+    validation.finish()?;
+
+    let matched_pubkey = pubkeys
+        .iter()
+        .find(|pubkey| signature.verify(pubkey.as_ref(), req.message.as_bytes()));
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: VerifyAnyResponse {
+            valid: matched_pubkey.is_some(),
+            matched_pubkey: matched_pubkey.map(Pubkey::to_string),
+        },
+    }))
+}
+
+/// Checks every item with a single ed25519 batch verification, which is much
+/// cheaper per signature than verifying one by one. Only when the batch fails
+/// are the items verified individually to find the bad ones. A passing batch
+/// uses the cofactored equation, which is slightly more permissive than the
+/// strict per-signature check `/message/verify` applies.
+async fn verify_message_batch_fast(
+    State(state): State<AppState>,
+    Json(req): Json<VerifyBatchRequest>,
+) -> Result<Json<SuccessResponse<VerifyBatchResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if req.items.is_empty() {
+        validation.fail("items", "must not be empty", "At least one item is required");
+    }
+    validation.batch_limit("items", req.items.len(), state.batch_limits.signatures, "items");
+    let items: Vec<(Pubkey, Signature)> = req
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            (
+                validation.pubkey(&format!("items[{}].pubkey", i), &item.pubkey, "Invalid public key"),
+                validation.signature(&format!("items[{}].signature", i), &item.signature),
+            )
+        })
+        .collect();
+    validation.finish()?;
+
+    let results = tokio::task::spawn_blocking(move || {
+        let parsed: Option<(Vec<ed25519_dalek::PublicKey>, Vec<ed25519_dalek::Signature>)> = items
+            .iter()
+            .map(|(pubkey, signature)| {
+                Some((
+                    ed25519_dalek::PublicKey::from_bytes(pubkey.as_ref()).ok()?,
+                    ed25519_dalek::Signature::try_from(signature.as_ref()).ok()?,
+                ))
+            })
+            .collect();
+        if let Some((public_keys, signatures)) = parsed {
+            let messages: Vec<&[u8]> = req.items.iter().map(|item| item.message.as_bytes()).collect();
+            if ed25519_dalek::verify_batch(&messages, &signatures, &public_keys).is_ok() {
+                return vec![true; items.len()];
+            }
+        }
+        items
+            .iter()
+            .zip(&req.items)
+            .map(|((pubkey, signature), item)| signature.verify(pubkey.as_ref(), item.message.as_bytes()))
+            .collect::<Vec<bool>>()
+    })
+    .await
+    .expect("batch verification does not panic");
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: VerifyBatchResponse {
+            all_valid: results.iter().all(|valid| *valid),
+            results,
+        },
+    }))
+}
+
+async fn convert_signature(
+    Json(req): Json<ConvertSignatureRequest>,
+) -> Result<Json<SuccessResponse<ConvertSignatureResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let from = validation.encoding("from", &req.from);
+    let to = validation.encoding("to", &req.to);
+    validation.finish()?;
+
+    let bytes = match from.decode(&req.signature) {
+        Some(bytes) => bytes,
+        None => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new(&format!("Invalid signature; not valid {}", from.name()))),
+            ))
+        }
+    };
+    if bytes.len() != 64 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new(&format!(
+                "Invalid signature length; expected 64 bytes, got {}",
+                bytes.len()
+            ))),
+        ));
+    }
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: ConvertSignatureResponse {
+            signature: to.encode(&bytes),
+            encoding: to.name(),
+        },
+    }))
+}
+
+fn build_send_sol(req: &SendSolRequest) -> Result<Instruction, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let from_pubkey = validation.pubkey("from", &req.from, "Invalid 'from' public key");
+    let to_pubkey = validation.pubkey("to", &req.to, "Invalid 'to' public key");
+
+    if validation.is_valid("from") && validation.is_valid("to") && from_pubkey == to_pubkey {
+        validation.fail(
+            "to",
+            "must differ from the sender",
+            "Sender and recipient addresses cannot be the same.",
+        );
+    }
+    if req.lamports == 0 {
+        validation.fail("lamports", "must be greater than 0", "Cannot send 0 lamports.");
+    }
+    validation.finish()?;
+
+    Ok(system_instruction::transfer(&from_pubkey, &to_pubkey, req.lamports))
+}
+
+/// With `ensureRentExempt`, a transfer that would create the recipient below
+/// the rent-exempt minimum is raised to that minimum, so it never leaves behind
+/// an account that cannot hold its balance.
+async fn send_sol(
+    State(state): State<AppState>,
+    uri: Uri,
+    Json(mut req): Json<SendSolRequest>,
+) -> Result<Json<SuccessResponse<SendSolResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut instruction = build_send_sol(&req)?;
+
+    let rent_exempt = if req.ensure_rent_exempt {
+        let rpc = rpc_client(&state)?;
+        let recipient = instruction.accounts[1].pubkey;
+        let (account, minimum_lamports) = tokio::try_join!(
+            rpc.get_account_with_commitment(&recipient, rpc.commitment()),
+            rpc.get_minimum_balance_for_rent_exemption(0),
+        )
+        .map_err(rpc_error)?;
+        let recipient_exists = account.value.is_some();
+        let adjusted = !recipient_exists && req.lamports < minimum_lamports;
+        if adjusted {
+            req.lamports = minimum_lamports;
+            instruction = build_send_sol(&req)?;
+        }
+        Some(RentExemptCheck {
+            recipient_exists,
+            minimum_lamports,
+            adjusted,
+            lamports: req.lamports,
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: SendSolResponse {
+            instruction: InstructionResponse {
+                compute_unit_hint: req
+                    .include_compute_hint
+                    .then(|| ComputeUnitHint::for_instructions([&instruction])),
+                meta: InstructionMeta::requested(&uri, "system_instruction::transfer", &instruction),
+                instruction: SerializableInstruction::expanded(instruction, req.include_program_accounts),
+            },
+            rent_exempt,
+        },
+    }))
+}
+
+fn build_send_token(req: &SendTokenRequest) -> Result<Instruction, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let destination_pubkey = validation.pubkey("destination", &req.destination, "Invalid destination public key");
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    validation.finish()?;
+
+    let source_token_account = get_associated_token_address(&owner_pubkey, &mint_pubkey);
+
+    spl_token::instruction::transfer(
+        &spl_token::ID,
+        &source_token_account,
+        &destination_pubkey,
+        &owner_pubkey,
+        &[],
+        req.amount,
+    )
+    .map_err(instruction_error)
+}
+
+/// Characters `encodeURIComponent` leaves alone, matching what Solana Pay
+/// wallets expect when parsing the URL.
+const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+/// A Solana Pay amount: a non-negative decimal without exponent or sign.
+fn is_pay_amount(amount: &str) -> bool {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, "0"));
+    !whole.is_empty()
+        && !fraction.is_empty()
+        && whole.bytes().all(|b| b.is_ascii_digit())
+        && fraction.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Builds a Solana Pay transfer request URL (`solana:<recipient>?...`).
+async fn pay_url(
+    Json(req): Json<PayUrlRequest>,
+) -> Result<Json<SuccessResponse<PayUrlResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let recipient = validation.pubkey("recipient", &req.recipient, "Invalid recipient public key");
+    let spl_token = validation.optional_pubkey("splToken", req.spl_token.as_deref(), "Invalid SPL token mint");
+    let reference = validation.optional_pubkey("reference", req.reference.as_deref(), "Invalid reference public key");
+    if let Some(amount) = req.amount.as_deref().filter(|amount| !is_pay_amount(amount)) {
+        validation.fail(
+            "amount",
+            &format!("{:?} is not a non-negative decimal", amount),
+            "Invalid amount; must be a decimal such as 1.5",
+        );
+    }
+    validation.finish()?;
+
+    let mut params = Vec::new();
+    if let Some(amount) = &req.amount {
+        params.push(("amount", amount.clone()));
+    }
+    if let Some(spl_token) = spl_token {
+        params.push(("spl-token", spl_token.to_string()));
+    }
+    if let Some(reference) = reference {
+        params.push(("reference", reference.to_string()));
+    }
+    if let Some(label) = &req.label {
+        params.push(("label", label.clone()));
+    }
+    if let Some(message) = &req.message {
+        params.push(("message", message.clone()));
+    }
+    let mut url = format!("solana:{}", recipient);
+    for (i, (key, value)) in params.iter().enumerate() {
+        url.push(if i == 0 { '?' } else { '&' });
+        url.push_str(key);
+        url.push('=');
+        url.extend(utf8_percent_encode(value, URI_COMPONENT));
+    }
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: PayUrlResponse { url },
+    }))
+}
+
+/// Converts a Solana Pay decimal amount into base units, rejecting amounts with
+/// more fractional digits than `decimals` or that overflow a u64.
+fn parse_pay_amount(amount: &str, decimals: u8) -> Option<u64> {
+    if !is_pay_amount(amount) {
+        return None;
+    }
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals as usize {
+        return None;
+    }
+    let digits = format!("{}{:0<width$}", whole, fraction, width = decimals as usize);
+    digits.parse::<u64>().ok()
+}
+
+/// Checks that a customer's transaction pays `recipient` exactly `amount`: a
+/// system transfer for SOL, or a `transfer_checked` of `splToken` into the
+/// recipient's ATA. When `reference` is given it must be one of the accounts
+/// of that transfer instruction, as Solana Pay wallets attach it there. Only
+/// static account keys are inspected; lookup-table addresses are not resolved.
+async fn pay_verify(
+    Json(req): Json<PayVerifyRequest>,
+) -> Result<Json<SuccessResponse<PayVerifyResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let recipient = validation.pubkey("recipient", &req.recipient, "Invalid recipient public key");
+    let spl_token = validation.optional_pubkey("splToken", req.spl_token.as_deref(), "Invalid SPL token mint");
+    let reference = validation.optional_pubkey("reference", req.reference.as_deref(), "Invalid reference public key");
+    if !is_pay_amount(&req.amount) {
+        validation.fail(
+            "amount",
+            &format!("{:?} is not a non-negative decimal", req.amount),
+            "Invalid amount; must be a decimal such as 1.5",
+        );
+    }
+    validation.finish()?;
+    let transaction = decode_transaction(&req.transaction)?;
+
+    let keys = transaction.message.static_account_keys();
+    let key = |index: &u8| keys.get(*index as usize);
+    let mut reason = match spl_token {
+        Some(_) => "No transfer_checked of the requested mint to the recipient's token account",
+        None => "No SOL transfer to the recipient",
+    };
+    let mut valid = false;
+    for instruction in transaction.message.instructions() {
+        let Some(program_id) = key(&instruction.program_id_index) else {
+            continue;
+        };
+        let accounts: Vec<&Pubkey> = instruction.accounts.iter().filter_map(key).collect();
+        let paid = match spl_token {
+            None if *program_id == solana_system_interface::program::ID => {
+                match unpack_system_instruction(&instruction.data) {
+                    Some(SystemInstruction::Transfer { lamports }) if accounts.get(1) == Some(&&recipient) => {
+                        Some(parse_pay_amount(&req.amount, 9) == Some(lamports))
+                    }
+                    _ => None,
+                }
+            }
+            Some(mint) if *program_id == spl_token::ID || *program_id == spl_token_2022::ID => {
+                let destination = get_associated_token_address_with_program_id(&recipient, &mint, program_id);
+                match TokenInstruction::unpack(&instruction.data) {
+                    Ok(TokenInstruction::TransferChecked { amount, decimals })
+                        if accounts.get(1) == Some(&&mint) && accounts.get(2) == Some(&&destination) =>
+                    {
+                        Some(parse_pay_amount(&req.amount, decimals) == Some(amount))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match paid {
+            Some(false) => reason = "Transfer to the recipient does not match the requested amount",
+            Some(true) if reference.is_some_and(|reference| !accounts.contains(&&reference)) => {
+                reason = "Transfer to the recipient does not include the reference key"
+            }
+            Some(true) => {
+                valid = true;
+                reason = "Transaction matches the payment request";
+                break;
+            }
+            None => {}
+        }
+    }
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: PayVerifyResponse {
+            valid,
+            reason: reason.to_string(),
+        },
+    }))
+}
+
+/// Where a value-moving instruction sends funds or control.
+enum PolicyRecipient {
+    /// Position among the instruction's accounts.
+    Account(usize),
+    /// Address carried in the instruction data, e.g. a new authority.
+    Address(Pubkey),
+}
+
+/// What a system, token or associated token instruction does, as far as a
+/// custody policy cares.
+#[derive(Default)]
+struct PolicyEffect {
+    recipient: Option<PolicyRecipient>,
+    lamports: u64,
+    /// Position of the mint, so an associated token account of an allowed
+    /// owner counts as an allowed recipient.
+    mint: Option<usize>,
+    /// Program an account is assigned to.
+    owner: Option<Pubkey>,
+    /// Releases an account's whole balance, which cannot be known offline.
+    unknown_lamports: bool,
+}
+
+/// Classifies an instruction for [`check_transaction_policy`]. Instructions
+/// of other programs have no effect here and are governed by
+/// `allowedPrograms` alone; a system or token instruction that does not
+/// decode or is not recognized is an error, so the check fails closed.
+fn policy_effect(program_id: &Pubkey, data: &[u8]) -> Result<PolicyEffect, String> {
+    use spl_token_2022::extension::transfer_fee::instruction::TransferFeeInstruction;
+    use spl_token_2022::instruction::TokenInstruction as Token2022Instruction;
+
+    let to = |position: usize, mint: Option<usize>| PolicyEffect {
+        recipient: Some(PolicyRecipient::Account(position)),
+        mint,
+        ..PolicyEffect::default()
+    };
+    if *program_id == solana_system_interface::program::ID {
+        let instruction = unpack_system_instruction(data)
+            .ok_or_else(|| "system instruction data does not decode".to_string())?;
+        return Ok(match instruction {
+            SystemInstruction::Transfer { lamports } => PolicyEffect { lamports, ..to(1, None) },
+            SystemInstruction::TransferWithSeed { lamports, .. } => PolicyEffect { lamports, ..to(2, None) },
+            SystemInstruction::CreateAccount { lamports, owner, .. }
+            | SystemInstruction::CreateAccountWithSeed { lamports, owner, .. } => PolicyEffect {
+                lamports,
+                owner: Some(owner),
+                ..to(1, None)
+            },
+            SystemInstruction::WithdrawNonceAccount(lamports) => PolicyEffect { lamports, ..to(1, None) },
+            SystemInstruction::InitializeNonceAccount(authority)
+            | SystemInstruction::AuthorizeNonceAccount(authority) => PolicyEffect {
+                recipient: Some(PolicyRecipient::Address(authority)),
+                ..PolicyEffect::default()
+            },
+            SystemInstruction::Assign { owner } | SystemInstruction::AssignWithSeed { owner, .. } => PolicyEffect {
+                owner: Some(owner),
+                ..PolicyEffect::default()
+            },
+            SystemInstruction::Allocate { .. }
+            | SystemInstruction::AllocateWithSeed { .. }
+            | SystemInstruction::AdvanceNonceAccount
+            | SystemInstruction::UpgradeNonceAccount => PolicyEffect::default(),
+        });
+    }
+    if *program_id == spl_token::ID || *program_id == spl_token_2022::ID {
+        // The Token-2022 layout is a superset of the original program's.
+        let instruction =
+            Token2022Instruction::unpack(data).map_err(|_| "token instruction data does not decode".to_string())?;
+        return Ok(match instruction {
+            #[allow(deprecated)]
+            Token2022Instruction::Transfer { .. } => to(1, None),
+            Token2022Instruction::TransferChecked { .. } => to(2, Some(1)),
+            Token2022Instruction::Approve { .. } => to(1, None),
+            Token2022Instruction::ApproveChecked { .. } => to(2, Some(1)),
+            Token2022Instruction::MintTo { .. } | Token2022Instruction::MintToChecked { .. } => to(1, Some(0)),
+            Token2022Instruction::CloseAccount | Token2022Instruction::WithdrawExcessLamports => PolicyEffect {
+                unknown_lamports: true,
+                ..to(1, None)
+            },
+            Token2022Instruction::SetAuthority { new_authority, .. } => PolicyEffect {
+                recipient: Option::<Pubkey>::from(new_authority).map(PolicyRecipient::Address),
+                ..PolicyEffect::default()
+            },
+            Token2022Instruction::TransferFeeExtension => {
+                match TransferFeeInstruction::unpack(&data[1..])
+                    .map_err(|_| "transfer fee instruction data does not decode".to_string())?
+                {
+                    TransferFeeInstruction::TransferCheckedWithFee { .. } => to(2, Some(1)),
+                    TransferFeeInstruction::WithdrawWithheldTokensFromMint
+                    | TransferFeeInstruction::WithdrawWithheldTokensFromAccounts { .. } => to(1, Some(0)),
+                    TransferFeeInstruction::InitializeTransferFeeConfig { .. }
+                    | TransferFeeInstruction::HarvestWithheldTokensToMint
+                    | TransferFeeInstruction::SetTransferFee { .. } => PolicyEffect::default(),
+                }
+            }
+            Token2022Instruction::InitializeMint { .. }
+            | Token2022Instruction::InitializeMint2 { .. }
+            | Token2022Instruction::InitializeAccount
+            | Token2022Instruction::InitializeAccount2 { .. }
+            | Token2022Instruction::InitializeAccount3 { .. }
+            | Token2022Instruction::InitializeMultisig { .. }
+            | Token2022Instruction::InitializeMultisig2 { .. }
+            | Token2022Instruction::InitializeImmutableOwner
+            | Token2022Instruction::Revoke
+            | Token2022Instruction::Burn { .. }
+            | Token2022Instruction::BurnChecked { .. }
+            | Token2022Instruction::FreezeAccount
+            | Token2022Instruction::ThawAccount
+            | Token2022Instruction::SyncNative
+            | Token2022Instruction::GetAccountDataSize { .. }
+            | Token2022Instruction::AmountToUiAmount { .. }
+            | Token2022Instruction::UiAmountToAmount { .. } => PolicyEffect::default(),
+            other => return Err(format!("token instruction {:?} is not recognized", other)),
+        });
+    }
+    if *program_id == spl_associated_token_account::ID {
+        // An empty payload is the legacy encoding of `Create`.
+        return match data {
+            [] | [0] | [1] => Ok(PolicyEffect::default()),
+            _ => Err("associated token account instruction is not recognized".to_string()),
+        };
+    }
+    Ok(PolicyEffect::default())
+}
+
+/// Checks a transaction against a custody policy and lists every breach rather
+/// than stopping at the first. Anything that moves lamports or tokens, or hands
+/// over an authority, delegate or account owner, is checked; a system or token
+/// instruction the service cannot decode or does not recognize is a violation
+/// in itself. Recipients loaded from an address lookup table cannot be
+/// resolved offline and count as violations, as do account closures under a
+/// `maxLamports` cap, since the balance they release is unknown.
+async fn check_transaction_policy(
+    Json(req): Json<CheckPolicyRequest>,
+) -> Result<Json<SuccessResponse<CheckPolicyResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let mut pubkeys = |field: &str, values: &Option<Vec<String>>, summary: &str| {
+        values.as_ref().map(|values| {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| validation.pubkey(&format!("policy.{}[{}]", field, i), value, summary))
+                .collect::<Vec<Pubkey>>()
+        })
+    };
+    let allowed_programs = pubkeys("allowedPrograms", &req.policy.allowed_programs, "Invalid allowed program id");
+    let allowed_recipients =
+        pubkeys("allowedRecipients", &req.policy.allowed_recipients, "Invalid allowed recipient public key");
+    validation.finish()?;
+    let transaction = decode_transaction(&req.transaction)?;
+
+    let keys = transaction.message.static_account_keys();
+    let mut violations = Vec::new();
+    let mut total_lamports = 0u64;
+    for (index, instruction) in transaction.message.instructions().iter().enumerate() {
+        let Some(program_id) = keys.get(instruction.program_id_index as usize) else {
+            continue;
+        };
+        if allowed_programs.as_ref().is_some_and(|allowed| !allowed.contains(program_id)) {
+            violations.push(PolicyViolation {
+                instruction: Some(index),
+                rule: "program",
+                detail: format!("invokes {}, which is not allowed", program_id),
+            });
+        }
+
+        let effect = match policy_effect(program_id, &instruction.data) {
+            Ok(effect) => effect,
+            Err(detail) => {
+                violations.push(PolicyViolation { instruction: Some(index), rule: "unrecognized", detail });
+                continue;
+            }
+        };
+        total_lamports = total_lamports.saturating_add(effect.lamports);
+        let disallowed = |owner: &Pubkey| allowed_programs.as_ref().is_some_and(|allowed| !allowed.contains(owner));
+        if let Some(owner) = effect.owner.filter(disallowed) {
+            violations.push(PolicyViolation {
+                instruction: Some(index),
+                rule: "program",
+                detail: format!("assigns an account to {}, which is not allowed", owner),
+            });
+        }
+        if effect.unknown_lamports && req.policy.max_lamports.is_some() {
+            violations.push(PolicyViolation {
+                instruction: Some(index),
+                rule: "maxLamports",
+                detail: "releases an account balance that cannot be known offline".to_string(),
+            });
+        }
+
+        let (Some(allowed), Some(recipient)) = (allowed_recipients.as_ref(), effect.recipient) else {
+            continue;
+        };
+        // `None` for an account that comes from a lookup table.
+        let account = |position: usize| instruction.accounts.get(position).map(|i| keys.get(*i as usize));
+        let recipient = match recipient {
+            PolicyRecipient::Address(address) => address,
+            PolicyRecipient::Account(position) => match account(position) {
+                None => continue,
+                Some(None) => {
+                    violations.push(PolicyViolation {
+                        instruction: Some(index),
+                        rule: "recipient",
+                        detail: "recipient is loaded from an address lookup table and cannot be checked".to_string(),
+                    });
+                    continue;
+                }
+                Some(Some(recipient)) => *recipient,
+            },
+        };
+        let mint = effect.mint.and_then(account).flatten();
+        let permitted = allowed.contains(&recipient)
+            || mint.is_some_and(|mint| {
+                allowed
+                    .iter()
+                    .any(|owner| get_associated_token_address_with_program_id(owner, mint, program_id) == recipient)
+            });
+        if !permitted {
+            violations.push(PolicyViolation {
+                instruction: Some(index),
+                rule: "recipient",
+                detail: format!("sends funds or control to {}, which is not allowed", recipient),
+            });
+        }
+    }
+    if let Some(max_lamports) = req.policy.max_lamports.filter(|max| total_lamports > *max) {
+        violations.push(PolicyViolation {
+            instruction: None,
+            rule: "maxLamports",
+            detail: format!("moves {} lamports, above the {} allowed", total_lamports, max_lamports),
+        });
+    }
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: CheckPolicyResponse {
+            compliant: violations.is_empty(),
+            total_lamports,
+            violations,
+        },
+    }))
+}
+
+/// Formats lamports as SOL without floating point, e.g. `1.5 SOL`.
+fn format_sol(lamports: u64) -> String {
+    format!("{} SOL", spl_token::amount_to_ui_amount_string_trimmed(lamports, 9))
+}
+
+/// One plain-English sentence for a system or token instruction, or `None`
+/// when it is not one we describe. `accounts` are already rendered, so lookup
+/// table entries read as such.
+fn summarize_instruction(program_id: &Pubkey, accounts: &[String], data: &[u8]) -> Option<String> {
+    let account = |i: usize| accounts.get(i).map(String::as_str).unwrap_or("?");
+    if *program_id == solana_system_interface::program::ID {
+        return Some(match unpack_system_instruction(data)? {
+            SystemInstruction::Transfer { lamports } => {
+                format!("Transfer {} from {} to {}", format_sol(lamports), account(0), account(1))
+            }
+            SystemInstruction::TransferWithSeed { lamports, .. } => {
+                format!("Transfer {} from {} to {}", format_sol(lamports), account(0), account(2))
+            }
+            SystemInstruction::CreateAccount { lamports, space, owner }
+            | SystemInstruction::CreateAccountWithSeed { lamports, space, owner, .. } => format!(
+                "Create account {} with {} and {} bytes, owned by {}, paid by {}",
+                account(1),
+                format_sol(lamports),
+                space,
+                owner,
+                account(0)
+            ),
+            SystemInstruction::Assign { owner } => format!("Assign account {} to program {}", account(0), owner),
+            SystemInstruction::Allocate { space } => format!("Allocate {} bytes for account {}", space, account(0)),
+            SystemInstruction::AdvanceNonceAccount => format!("Advance nonce account {}", account(0)),
+            SystemInstruction::WithdrawNonceAccount(lamports) => format!(
+                "Withdraw {} from nonce account {} to {}",
+                format_sol(lamports),
+                account(0),
+                account(1)
+            ),
+            SystemInstruction::InitializeNonceAccount(authority) => {
+                format!("Initialize nonce account {} with authority {}", account(0), authority)
+            }
+            SystemInstruction::AuthorizeNonceAccount(authority) => {
+                format!("Change the authority of nonce account {} to {}", account(0), authority)
+            }
+            _ => return None,
+        });
+    }
+    if *program_id == spl_associated_token_account::ID {
+        let action = match data.first() {
+            None | Some(0) => "Create",
+            Some(1) => "Create (if missing)",
+            _ => return None,
+        };
+        return Some(format!(
+            "{} token account {} for wallet {} and mint {}, paid by {}",
+            action,
+            account(1),
+            account(2),
+            account(3),
+            account(0)
+        ));
+    }
+    if *program_id != spl_token::ID && *program_id != spl_token_2022::ID {
+        return None;
+    }
+    let tokens = |amount: u64, decimals: Option<u8>| match decimals {
+        Some(decimals) => format!("{} tokens", spl_token::amount_to_ui_amount_string_trimmed(amount, decimals)),
+        None => format!("{} base units", amount),
+    };
+    Some(match TokenInstruction::unpack(data).ok()? {
+        TokenInstruction::Transfer { amount } => format!(
+            "Transfer {} from token account {} to {}",
+            tokens(amount, None),
+            account(0),
+            account(1)
+        ),
+        TokenInstruction::TransferChecked { amount, decimals } => format!(
+            "Transfer {} of mint {} from token account {} to {}",
+            tokens(amount, Some(decimals)),
+            account(1),
+            account(0),
+            account(2)
+        ),
+        TokenInstruction::MintTo { amount } => format!(
+            "Mint {} of mint {} to account {}",
+            tokens(amount, None),
+            account(0),
+            account(1)
+        ),
+        TokenInstruction::MintToChecked { amount, decimals } => format!(
+            "Mint {} of mint {} to account {}",
+            tokens(amount, Some(decimals)),
+            account(0),
+            account(1)
+        ),
+        TokenInstruction::Burn { amount } => format!(
+            "Burn {} of mint {} from account {}",
+            tokens(amount, None),
+            account(1),
+            account(0)
+        ),
+        TokenInstruction::BurnChecked { amount, decimals } => format!(
+            "Burn {} of mint {} from account {}",
+            tokens(amount, Some(decimals)),
+            account(1),
+            account(0)
+        ),
+        TokenInstruction::Approve { amount } => format!(
+            "Allow {} to spend {} from token account {}",
+            account(1),
+            tokens(amount, None),
+            account(0)
+        ),
+        TokenInstruction::Revoke => format!("Revoke the delegate of token account {}", account(0)),
+        TokenInstruction::CloseAccount => {
+            format!("Close token account {}, sending its lamports to {}", account(0), account(1))
+        }
+        TokenInstruction::SyncNative => format!("Sync the wrapped SOL balance of {}", account(0)),
+        TokenInstruction::FreezeAccount => format!("Freeze token account {}", account(0)),
+        TokenInstruction::ThawAccount => format!("Thaw token account {}", account(0)),
+        TokenInstruction::InitializeMint { decimals, mint_authority, .. }
+        | TokenInstruction::InitializeMint2 { decimals, mint_authority, .. } => format!(
+            "Initialize mint {} with {} decimals and mint authority {}",
+            account(0),
+            decimals,
+            mint_authority
+        ),
+        TokenInstruction::InitializeAccount => {
+            format!("Initialize token account {} for mint {} owned by {}", account(0), account(1), account(2))
+        }
+        TokenInstruction::InitializeAccount2 { owner } | TokenInstruction::InitializeAccount3 { owner } => {
+            format!("Initialize token account {} for mint {} owned by {}", account(0), account(1), owner)
+        }
+        TokenInstruction::SetAuthority { authority_type, new_authority } => {
+            let kind = match authority_type {
+                AuthorityType::MintTokens => "mint",
+                AuthorityType::FreezeAccount => "freeze",
+                AuthorityType::AccountOwner => "owner",
+                AuthorityType::CloseAccount => "close",
+            };
+            match Option::<Pubkey>::from(new_authority) {
+                Some(authority) => format!("Set the {} authority of {} to {}", kind, account(0), authority),
+                None => format!("Remove the {} authority of {} permanently", kind, account(0)),
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// Renders a transaction as one sentence per instruction for a confirmation
+/// screen. System, token and associated-token instructions are described;
+/// anything else reads "Call program P with N accounts". Accounts loaded from
+/// lookup tables cannot be named offline and appear as their table position.
+/// Transactions that fail sanitization are rejected rather than summarized.
+async fn transaction_summary(
+    Json(req): Json<TransactionSummaryRequest>,
+) -> Result<Json<SuccessResponse<TransactionSummaryResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let transaction = decode_transaction(&req.transaction)?;
+    // A summary is what a user approves, so the whole transaction must be well
+    // formed, signature slots included, before anything is described.
+    let invalid = || (StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid transaction")));
+    transaction.sanitize().map_err(|_| invalid())?;
+
+    let keys = transaction.message.static_account_keys();
+    let name = |index: u8| match keys.get(index as usize) {
+        Some(key) => key.to_string(),
+        None => format!("lookup table account #{}", index as usize - keys.len()),
+    };
+    let lines = transaction
+        .message
+        .instructions()
+        .iter()
+        .map(|instruction| {
+            let program_id = keys.get(instruction.program_id_index as usize).ok_or_else(invalid)?;
+            let accounts: Vec<String> = instruction.accounts.iter().map(|index| name(*index)).collect();
+            Ok(summarize_instruction(program_id, &accounts, &instruction.data).unwrap_or_else(|| {
+                let count = instruction.accounts.len();
+                format!("Call program {} with {} account{}", program_id, count, if count == 1 { "" } else { "s" })
+            }))
+        })
+        .collect::<Result<Vec<String>, _>>()?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: TransactionSummaryResponse {
+            fee_payer: keys.first().map(Pubkey::to_string).unwrap_or_default(),
+            lines,
+        },
+    }))
+}
+
+/// Checks that `from` can cover a transfer of `lamports` plus the fee without
+/// dropping below the rent-exempt minimum.
+async fn send_sol_preflight(
+    State(state): State<AppState>,
+    Json(req): Json<SolPreflightRequest>,
+) -> Result<Json<SuccessResponse<SolPreflightResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let from_pubkey = validation.pubkey("from", &req.from, "Invalid sender public key");
+    validation.finish()?;
+
+    let rpc = rpc_client(&state)?;
+    let (balance, rent_reserve) = tokio::try_join!(
+        rpc.get_balance(&from_pubkey),
+        rpc.get_minimum_balance_for_rent_exemption(0),
+    )
+    .map_err(rpc_error)?;
+    let estimated_fee = LAMPORTS_PER_SIGNATURE;
+    let required = req.lamports.saturating_add(estimated_fee).saturating_add(rent_reserve);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: SolPreflightResponse {
+            balance,
+            estimated_fee,
+            rent_reserve,
+            required,
+            sufficient: balance >= required,
+            shortfall: required.saturating_sub(balance),
+        },
+    }))
+}
+
+/// One `transfer_checked` per recipient, all drawn from the owner's ATA.
+async fn send_token_split(
+    State(state): State<AppState>,
+    Json(req): Json<TokenSplitRequest>,
+) -> Result<Json<SuccessResponse<TokenSplitResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    if req.decimals > MAX_TOKEN_DECIMALS {
+        validation.fail(
+            "decimals",
+            &format!("must be at most {}", MAX_TOKEN_DECIMALS),
+            "Decimals cannot exceed 9",
+        );
+    }
+    if req.recipients.is_empty() {
+        validation.fail("recipients", "must not be empty", "At least one recipient is required");
+    }
+    validation.batch_limit("recipients", req.recipients.len(), state.batch_limits.transfers, "recipients");
+    let mut total = Some(0u64);
+    let recipients: Vec<(Pubkey, u64)> = req
+        .recipients
+        .iter()
+        .enumerate()
+        .map(|(i, recipient)| {
+            let ata = validation.pubkey(&format!("recipients[{}].ata", i), &recipient.ata, "Invalid recipient public key");
+            if recipient.amount == 0 {
+                validation.fail(
+                    &format!("recipients[{}].amount", i),
+                    "must be greater than 0",
+                    "Cannot transfer 0 tokens",
+                );
+            }
+            total = total.and_then(|total| total.checked_add(recipient.amount));
+            (ata, recipient.amount)
+        })
+        .collect();
+    if total.is_none() {
+        validation.fail("recipients", "total amount overflows u64", "Total transfer amount is too large");
+    }
+    validation.finish()?;
+
+    let source = get_associated_token_address(&owner_pubkey, &mint_pubkey);
+    let instructions = recipients
+        .iter()
+        .map(|(ata, amount)| {
+            spl_token::instruction::transfer_checked(
+                &spl_token::ID,
+                &source,
+                &mint_pubkey,
+                ata,
+                &owner_pubkey,
+                &[],
+                *amount,
+                req.decimals,
+            )
+            .map(SerializableInstruction::from)
+            .map_err(instruction_error)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: TokenSplitResponse {
+            instructions,
+            total: total.expect("validated above"),
+        },
+    }))
+}
+
+/// Thaw, `transfer_checked`, freeze: moves tokens into an account that is kept
+/// frozen between movements, as with a mint whose default account state is
+/// frozen.
+async fn gated_transfer(
+    Json(req): Json<GatedTransferRequest>,
+) -> Result<Json<SuccessResponse<InstructionListResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let source_pubkey = validation.pubkey("source", &req.source, "Invalid source public key");
+    let destination_pubkey = validation.pubkey("destination", &req.destination, "Invalid destination public key");
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let authority_pubkey = validation.pubkey("authority", &req.authority, "Invalid freeze authority public key");
+    let program_id = validation.token_program("program", req.program.as_deref());
+    if req.decimals > MAX_TOKEN_DECIMALS {
+        validation.fail(
+            "decimals",
+            &format!("must be at most {}", MAX_TOKEN_DECIMALS),
+            "Decimals cannot exceed 9",
+        );
+    }
+    if req.amount == 0 {
+        validation.fail("amount", "must be greater than 0", "Cannot transfer 0 tokens");
+    }
+    validation.finish()?;
+
+    let instructions = vec![
+        spl_token_2022::instruction::thaw_account(
+            &program_id,
+            &destination_pubkey,
+            &mint_pubkey,
+            &authority_pubkey,
+            &[],
+        )
+        .map_err(instruction_error)?,
+        spl_token_2022::instruction::transfer_checked(
+            &program_id,
+            &source_pubkey,
+            &mint_pubkey,
+            &destination_pubkey,
+            &owner_pubkey,
+            &[],
+            req.amount,
+            req.decimals,
+        )
+        .map_err(instruction_error)?,
+        spl_token_2022::instruction::freeze_account(
+            &program_id,
+            &destination_pubkey,
+            &mint_pubkey,
+            &authority_pubkey,
+            &[],
+        )
+        .map_err(instruction_error)?,
+    ];
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InstructionListResponse {
+            instructions: instructions.into_iter().map(SerializableInstruction::from).collect(),
+        },
+    }))
+}
+
+async fn send_token(
+    uri: Uri,
+    Json(req): Json<SendTokenRequest>,
+) -> Result<Json<SuccessResponse<InstructionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let instruction = build_send_token(&req)?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InstructionResponse {
+            compute_unit_hint: req
+                .include_compute_hint
+                .then(|| ComputeUnitHint::for_instructions([&instruction])),
+            meta: InstructionMeta::requested(&uri, "spl_token::transfer", &instruction),
+            instruction: SerializableInstruction::expanded(instruction, req.include_program_accounts),
+        },
+    }))
+}
+
+async fn estimate_fee(
+    State(state): State<AppState>,
+    Json(req): Json<EstimateFeeRequest>,
+) -> Result<Json<SuccessResponse<EstimateFeeResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let message = decode_message(&req.message)?;
+
+    let rpc = rpc_client(&state)?;
+    let fee = match &message {
+        VersionedMessage::Legacy(message) => rpc.get_fee_for_message(message).await,
+        VersionedMessage::V0(message) => rpc.get_fee_for_message(message).await,
+    };
+    let lamports = match fee {
+        Ok(fee) => fee,
+        // The RPC answers `null` for a blockhash it no longer recognises.
+        Err(e) if matches!(e.kind(), ClientErrorKind::Custom(msg) if msg == "Invalid blockhash") => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new(
+                    "Blockhash in the message has expired; rebuild the transaction with a recent blockhash",
+                )),
+            ))
+        }
+        Err(e) => return Err(rpc_error(e)),
+    };
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: EstimateFeeResponse {
+            lamports,
+            sol: lamports as f64 / LAMPORTS_PER_SOL as f64,
+        },
+    }))
+}
+
+/// Rent-exempt minimum for a well-known account type, for clients that would
+/// otherwise have to know its size in bytes.
+async fn rent_by_type(
+    State(state): State<AppState>,
+    Json(req): Json<RentByTypeRequest>,
+) -> Result<Json<SuccessResponse<RentByTypeResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let space = ACCOUNT_TYPE_SIZES
+        .iter()
+        .find(|(name, _)| *name == req.account_type)
+        .map(|(_, space)| *space);
+    if space.is_none() {
+        let names: Vec<&str> = ACCOUNT_TYPE_SIZES.iter().map(|(name, _)| *name).collect();
+        validation.fail("type", &format!("must be one of {}", names.join(", ")), "Unknown account type");
+    }
+    validation.finish()?;
+    let space = space.expect("validated above");
+
+    let lamports = match state.rpc.as_deref() {
+        Some(rpc) => rpc.get_minimum_balance_for_rent_exemption(space).await.map_err(rpc_error)?,
+        None => Rent::default().minimum_balance(space),
+    };
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: RentByTypeResponse {
+            account_type: req.account_type,
+            space,
+            lamports,
+        },
+    }))
+}
+
+/// Sums what the payer needs for a batch: rent for every new account, every
+/// transfer, and the base fee for one payer signature per transaction plus one
+/// for each new account. Priority fees are not included.
+async fn estimate_funding(
+    State(state): State<AppState>,
+    Json(req): Json<EstimateFundingRequest>,
+) -> Result<Json<SuccessResponse<EstimateFundingResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if req.operations.is_empty() {
+        validation.fail("operations", "must not be empty", "At least one operation is required");
+    }
+    validation.batch_limit("operations", req.operations.len(), state.batch_limits.funding_operations, "operations");
+    for (i, operation) in req.operations.iter().enumerate() {
+        if let FundingOperation::CreateAccount { space } = operation
+            && *space > MAX_PERMITTED_DATA_LENGTH
+        {
+            validation.fail(
+                &format!("operations[{}].space", i),
+                &format!("must be at most {}", MAX_PERMITTED_DATA_LENGTH),
+                "Account space is too large",
+            );
+        }
+    }
+    if req.transactions == Some(0) {
+        validation.fail("transactions", "must be at least 1", "At least one transaction is required");
+    }
+    validation.finish()?;
+
+    let rent = match state.rpc.as_deref() {
+        Some(rpc) => {
+            let account = rpc.get_account(&solana_sdk::sysvar::rent::ID).await.map_err(rpc_error)?;
+            bincode::deserialize::<Rent>(&account.data).map_err(|_| {
+                (
+                    StatusCode::BAD_GATEWAY,
+                    Json(ErrorResponse::new("Cluster returned an invalid rent sysvar")),
+                )
+            })?
+        }
+        None => Rent::default(),
+    };
+
+    let overflow = || {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("Total funding is too large")),
+        )
+    };
+    let mut rent_lamports = 0u64;
+    let mut transfer_lamports = 0u64;
+    let mut signatures = req.transactions.unwrap_or(req.operations.len() as u64);
+    for operation in &req.operations {
+        match operation {
+            FundingOperation::CreateAccount { space } => {
+                rent_lamports = rent_lamports
+                    .checked_add(rent.minimum_balance(*space as usize))
+                    .ok_or_else(overflow)?;
+                signatures = signatures.checked_add(1).ok_or_else(overflow)?;
+            }
+            FundingOperation::Transfer { lamports } => {
+                transfer_lamports = transfer_lamports.checked_add(*lamports).ok_or_else(overflow)?;
+            }
+        }
+    }
+    let fee_lamports = signatures.checked_mul(LAMPORTS_PER_SIGNATURE).ok_or_else(overflow)?;
+    let total_lamports = rent_lamports
+        .checked_add(transfer_lamports)
+        .and_then(|total| total.checked_add(fee_lamports))
+        .ok_or_else(overflow)?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: EstimateFundingResponse {
+            rent_lamports,
+            transfer_lamports,
+            fee_lamports,
+            total_lamports,
+            sol: total_lamports as f64 / LAMPORTS_PER_SOL as f64,
+        },
+    }))
+}
+
+async fn build_transaction(
+    State(state): State<AppState>,
+    Json(req): Json<BuildTransactionRequest>,
+) -> Result<Json<SuccessResponse<BuildTransactionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let fee_payer = validation.pubkey("feePayer", &req.fee_payer, "Invalid fee payer public key");
+    if req.instructions.is_empty() {
+        validation.fail("instructions", "must not be empty", "At least one instruction is required");
+    }
+    let instructions: Vec<Instruction> = req
+        .instructions
+        .iter()
+        .enumerate()
+        .map(|(i, ix)| ix.to_instruction(&format!("instructions[{}]", i), &mut validation))
+        .collect();
+    let supplied_blockhash = req.blockhash.as_deref().map(|value| match Hash::from_str(value) {
+        Ok(hash) => hash,
+        Err(_) => {
+            validation.fail("blockhash", "invalid blockhash", "Invalid blockhash");
+            Hash::default()
+        }
+    });
+    validation.finish()?;
+
+    let (blockhash, last_valid_block_height) = match supplied_blockhash {
+        Some(hash) => (hash, None),
+        None => {
+            let rpc = rpc_client(&state)?;
+            let (hash, height) = rpc
+                .get_latest_blockhash_with_commitment(rpc.commitment())
+                .await
+                .map_err(rpc_error)?;
+            (hash, Some(height))
+        }
+    };
+
+    let message = Message::new_with_blockhash(&instructions, Some(&fee_payer), &blockhash);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: BuildTransactionResponse {
+            message: general_purpose::STANDARD.encode(message.serialize()),
+            blockhash: blockhash.to_string(),
+            last_valid_block_height,
+            required_signers: required_signers(&message),
+        },
+    }))
+}
+
+/// Compiles a legacy message against a caller-supplied blockhash without any
+/// RPC, for signing setups that never touch the cluster.
+async fn compile_message(
+    State(state): State<AppState>,
+    Json(req): Json<CompileMessageRequest>,
+) -> Result<Json<SuccessResponse<BuildTransactionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let fee_payer = validation.pubkey("feePayer", &req.fee_payer, "Invalid fee payer public key");
+    let blockhash = Hash::from_str(&req.blockhash).unwrap_or_else(|_| {
+        validation.fail("blockhash", "invalid blockhash", "Invalid blockhash");
+        Hash::default()
+    });
+    let instructions: Vec<Instruction> = match (&req.instructions, &req.operations) {
+        (Some(instructions), None) => {
+            if instructions.is_empty() {
+                validation.fail("instructions", "must not be empty", "At least one instruction is required");
+            }
+            instructions
+                .iter()
+                .enumerate()
+                .map(|(i, ix)| ix.to_instruction(&format!("instructions[{}]", i), &mut validation))
+                .collect()
+        }
+        (None, Some(operations)) => {
+            if operations.is_empty() {
+                validation.fail("operations", "must not be empty", "At least one operation is required");
+            }
+            Vec::new()
+        }
+        _ => {
+            validation.fail(
+                "instructions",
+                "provide exactly one of instructions or operations",
+                "Provide exactly one of instructions or operations",
+            );
+            Vec::new()
+        }
+    };
+    validation.finish()?;
+    let instructions = match &req.operations {
+        Some(operations) => build_operations(operations, &state)?,
+        None => instructions,
+    };
+
+    let message = Message::new_with_blockhash(&instructions, Some(&fee_payer), &blockhash);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: BuildTransactionResponse {
+            message: general_purpose::STANDARD.encode(message.serialize()),
+            blockhash: blockhash.to_string(),
+            last_valid_block_height: None,
+            required_signers: required_signers(&message),
+        },
+    }))
+}
+
+/// The first `num_required_signatures` account keys, which line up one to one
+/// with the transaction's signatures; the fee payer always comes first.
+fn required_signers(message: &Message) -> Vec<String> {
+    message
+        .account_keys
+        .iter()
+        .take(message.header.num_required_signatures as usize)
+        .map(Pubkey::to_string)
+        .collect()
+}
+
+/// Compiles a durable-nonce message: `advance_nonce_account` goes first and the
+/// stored nonce stands in for the blockhash, so the transaction never expires.
+async fn build_transaction_with_nonce(
+    Json(req): Json<BuildWithNonceRequest>,
+) -> Result<Json<SuccessResponse<BuildTransactionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let fee_payer = validation.pubkey("feePayer", &req.fee_payer, "Invalid fee payer public key");
+    let nonce_account = validation.pubkey("nonceAccount", &req.nonce_account, "Invalid nonce account public key");
+    let nonce_authority =
+        validation.pubkey("nonceAuthority", &req.nonce_authority, "Invalid nonce authority public key");
+    let nonce = Hash::from_str(&req.nonce).unwrap_or_else(|_| {
+        validation.fail("nonce", "invalid nonce", "Invalid nonce; must be a base58 hash");
+        Hash::default()
+    });
+    if req.instructions.is_empty() {
+        validation.fail("instructions", "must not be empty", "At least one instruction is required");
+    }
+    let mut instructions = vec![system_instruction::advance_nonce_account(&nonce_account, &nonce_authority)];
+    instructions.extend(
+        req.instructions
+            .iter()
+            .enumerate()
+            .map(|(i, ix)| ix.to_instruction(&format!("instructions[{}]", i), &mut validation)),
+    );
+    validation.finish()?;
+
+    let message = Message::new_with_blockhash(&instructions, Some(&fee_payer), &nonce);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: BuildTransactionResponse {
+            message: general_purpose::STANDARD.encode(message.serialize()),
+            blockhash: nonce.to_string(),
+            last_valid_block_height: None,
+            required_signers: required_signers(&message),
+        },
+    }))
+}
+
+/// The full durable-nonce setup from `create_nonce_account`. Underfunding is an
+/// error rather than a warning: a nonce account below the rent-exempt minimum
+/// cannot be created at all.
+async fn create_nonce_account(
+    State(state): State<AppState>,
+    Json(req): Json<CreateNonceRequest>,
+) -> Result<Json<SuccessResponse<CreateNonceResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let payer_pubkey = validation.pubkey("payer", &req.payer, "Invalid payer public key");
+    let nonce_pubkey = validation.pubkey("nonceAccount", &req.nonce_account, "Invalid nonce account public key");
+    let authority_pubkey = validation.pubkey("authority", &req.authority, "Invalid authority public key");
+    if req.payer == req.nonce_account {
+        validation.fail("nonceAccount", "must differ from payer", "Nonce account cannot be the payer");
+    }
+    validation.finish()?;
+
+    let space = solana_sdk::nonce::state::State::size();
+    let rent_lamports = match state.rpc.as_deref() {
+        Some(rpc) => rpc.get_minimum_balance_for_rent_exemption(space).await.map_err(rpc_error)?,
+        None => Rent::default().minimum_balance(space),
+    };
+    let mut validation = Validation::default();
+    if req.lamports < rent_lamports {
+        validation.fail(
+            "lamports",
+            &format!("must be at least {} (rent-exempt minimum)", rent_lamports),
+            "Not enough lamports for a rent-exempt nonce account",
+        );
+    }
+    validation.finish()?;
+
+    let instructions =
+        system_instruction::create_nonce_account(&payer_pubkey, &nonce_pubkey, &authority_pubkey, req.lamports);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: CreateNonceResponse {
+            instructions: instructions.into_iter().map(SerializableInstruction::from).collect(),
+            rent_lamports,
+        },
+    }))
+}
+
+/// Hands a nonce account over to `newAuthority`, for rotating the key that
+/// advances it.
+async fn authorize_nonce_account(
+    uri: Uri,
+    Json(req): Json<AuthorizeNonceRequest>,
+) -> Result<Json<SuccessResponse<InstructionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let nonce_pubkey = validation.pubkey("nonceAccount", &req.nonce_account, "Invalid nonce account public key");
+    let current_authority =
+        validation.pubkey("currentAuthority", &req.current_authority, "Invalid current authority public key");
+    let new_authority = validation.pubkey("newAuthority", &req.new_authority, "Invalid new authority public key");
+    validation.finish()?;
+
+    let instruction = system_instruction::authorize_nonce_account(&nonce_pubkey, &current_authority, &new_authority);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InstructionResponse {
+            compute_unit_hint: None,
+            meta: InstructionMeta::requested(&uri, "system_instruction::authorize_nonce_account", &instruction),
+            instruction: instruction.into(),
+        },
+    }))
+}
+
+/// Builds create-ATA (idempotent) + transfer + sync_native to wrap `lamports`
+/// of the owner's SOL into their native-mint token account. Returns the ATA
+/// alongside the instructions.
+fn build_wrap_sol(req: &WrapSolRequest) -> Result<(Pubkey, Vec<Instruction>), (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    let funder_pubkey = validation.pubkey("funder", &req.funder, "Invalid funder public key");
+    if req.lamports == 0 {
+        validation.fail("lamports", "must be greater than 0", "Cannot wrap 0 lamports.");
+    }
+    validation.finish()?;
+
+    let ata = get_associated_token_address(&owner_pubkey, &spl_token::native_mint::ID);
+    let instructions = vec![
+        create_associated_token_account_idempotent(
+            &funder_pubkey,
+            &owner_pubkey,
+            &spl_token::native_mint::ID,
+            &spl_token::ID,
+        ),
+        system_instruction::transfer(&owner_pubkey, &ata, req.lamports),
+        spl_token::instruction::sync_native(&spl_token::ID, &ata).map_err(instruction_error)?,
+    ];
+    Ok((ata, instructions))
+}
+
+async fn wrap_sol(
+    Json(req): Json<WrapSolRequest>,
+) -> Result<Json<SuccessResponse<WsolResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let (ata, instructions) = build_wrap_sol(&req)?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: WsolResponse {
+            ata: ata.to_string(),
+            instructions: instructions.into_iter().map(SerializableInstruction::from).collect(),
+        },
+    }))
+}
+
+/// Idempotent create-ATA for the owner's wSOL account, without the transfer and
+/// `sync_native` that `/token/wrap-sol` adds.
+async fn create_native_account(
+    Json(req): Json<CreateNativeAccountRequest>,
+) -> Result<Json<SuccessResponse<CreateNativeAccountResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    let funder_pubkey = validation.pubkey("funder", &req.funder, "Invalid funder public key");
+    validation.finish()?;
+
+    let ata = get_associated_token_address(&owner_pubkey, &spl_token::native_mint::ID);
+    let instruction = create_associated_token_account_idempotent(
+        &funder_pubkey,
+        &owner_pubkey,
+        &spl_token::native_mint::ID,
+        &spl_token::ID,
+    );
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: CreateNativeAccountResponse {
+            ata: ata.to_string(),
+            instruction: instruction.into(),
+        },
+    }))
+}
+
+/// Closes the owner's wSOL ATA, which unwraps its full balance into
+/// `destination`, optionally after a `sync_native`.
+fn build_unwrap_sol(req: &UnwrapSolRequest) -> Result<(Pubkey, Vec<Instruction>), (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    let destination_pubkey = validation.pubkey("destination", &req.destination, "Invalid destination public key");
+    validation.finish()?;
+
+    let ata = get_associated_token_address(&owner_pubkey, &spl_token::native_mint::ID);
+    let mut instructions = Vec::new();
+    if req.sync_first {
+        instructions.push(spl_token::instruction::sync_native(&spl_token::ID, &ata).map_err(instruction_error)?);
+    }
+    instructions.push(
+        spl_token::instruction::close_account(&spl_token::ID, &ata, &destination_pubkey, &owner_pubkey, &[])
+            .map_err(instruction_error)?,
+    );
+    Ok((ata, instructions))
+}
+
+async fn unwrap_sol(
+    Json(req): Json<UnwrapSolRequest>,
+) -> Result<Json<SuccessResponse<WsolResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let (ata, instructions) = build_unwrap_sol(&req)?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: WsolResponse {
+            ata: ata.to_string(),
+            instructions: instructions.into_iter().map(SerializableInstruction::from).collect(),
+        },
+    }))
+}
+
+/// `allocate_with_seed` then `assign_with_seed` for an account derived from
+/// `base`, for seed-derived accounts that already hold lamports and so cannot
+/// go through `create_account_with_seed`.
+async fn allocate_assign_with_seed(
+    Json(req): Json<AllocateAssignWithSeedRequest>,
+) -> Result<Json<SuccessResponse<InstructionListResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let account_pubkey = validation.pubkey("account", &req.account, "Invalid account public key");
+    let base_pubkey = validation.pubkey("base", &req.base, "Invalid base public key");
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    if req.seed.len() > MAX_SEED_LEN {
+        validation.fail(
+            "seed",
+            &format!("must be at most {} bytes", MAX_SEED_LEN),
+            "Seed is too long",
+        );
+    }
+    if req.space > MAX_PERMITTED_DATA_LENGTH {
+        validation.fail(
+            "space",
+            &format!("must be at most {}", MAX_PERMITTED_DATA_LENGTH),
+            "Account space is too large",
+        );
+    }
+    validation.finish()?;
+
+    let mut validation = Validation::default();
+    match Pubkey::create_with_seed(&base_pubkey, &req.seed, &owner_pubkey) {
+        Ok(derived) if derived == account_pubkey => {}
+        Ok(derived) => validation.fail(
+            "account",
+            &format!("does not match the address derived from base, seed and owner ({})", derived),
+            "Account is not derived from base, seed and owner",
+        ),
+        Err(_) => validation.fail("owner", "may not be used as a seed owner", "Invalid seed owner"),
+    }
+    validation.finish()?;
+
+    let instructions = vec![
+        system_instruction::allocate_with_seed(&account_pubkey, &base_pubkey, &req.seed, req.space, &owner_pubkey),
+        system_instruction::assign_with_seed(&account_pubkey, &base_pubkey, &req.seed, &owner_pubkey),
+    ];
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InstructionListResponse {
+            instructions: instructions.into_iter().map(SerializableInstruction::from).collect(),
+        },
+    }))
+}
+
+/// `create_account_with_seed` at the address derived from `base`, `seed` and
+/// `owner`, returned alongside the instruction.
+async fn create_account_with_seed(
+    Json(req): Json<CreateAccountWithSeedRequest>,
+) -> Result<Json<SuccessResponse<CreateAccountWithSeedResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let from_pubkey = validation.pubkey("from", &req.from, "Invalid funding public key");
+    let base_pubkey = validation.pubkey("base", &req.base, "Invalid base public key");
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    if req.seed.len() > MAX_SEED_LEN {
+        validation.fail(
+            "seed",
+            &format!("must be at most {} bytes", MAX_SEED_LEN),
+            "Seed is too long",
+        );
+    }
+    if req.space > MAX_PERMITTED_DATA_LENGTH {
+        validation.fail(
+            "space",
+            &format!("must be at most {}", MAX_PERMITTED_DATA_LENGTH),
+            "Account space is too large",
+        );
+    }
+    validation.finish()?;
+
+    let mut validation = Validation::default();
+    let address = Pubkey::create_with_seed(&base_pubkey, &req.seed, &owner_pubkey).unwrap_or_else(|_| {
+        validation.fail("owner", "may not be used as a seed owner", "Invalid seed owner");
+        Pubkey::default()
+    });
+    validation.finish()?;
+    let instruction = system_instruction::create_account_with_seed(
+        &from_pubkey,
+        &address,
+        &base_pubkey,
+        &req.seed,
+        req.lamports,
+        req.space,
+        &owner_pubkey,
+    );
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: CreateAccountWithSeedResponse {
+            address: address.to_string(),
+            instruction: instruction.into(),
+        },
+    }))
+}
+
+/// One `close_account` per listed account, capped at `MAX_BATCH_CLOSE_ACCOUNTS`.
+async fn close_many(
+    State(state): State<AppState>,
+    Json(req): Json<CloseManyRequest>,
+) -> Result<Json<SuccessResponse<InstructionListResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    let destination_pubkey =
+        validation.pubkey("rentDestination", &req.rent_destination, "Invalid rent destination public key");
+    if req.accounts.is_empty() {
+        validation.fail("accounts", "must not be empty", "At least one account is required");
+    }
+    validation.batch_limit("accounts", req.accounts.len(), state.batch_limits.close_accounts, "accounts");
+    let accounts: Vec<Pubkey> = req
+        .accounts
+        .iter()
+        .enumerate()
+        .map(|(i, account)| validation.pubkey(&format!("accounts[{}]", i), account, "Invalid account public key"))
+        .collect();
+    validation.finish()?;
+
+    let instructions = accounts
+        .iter()
+        .map(|account| {
+            spl_token::instruction::close_account(&spl_token::ID, account, &destination_pubkey, &owner_pubkey, &[])
+                .map(SerializableInstruction::from)
+                .map_err(instruction_error)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InstructionListResponse { instructions },
+    }))
+}
+
+/// The ATA depends on which token program owns the mint, so both are returned.
+async fn derive_addresses(
+    Json(req): Json<DeriveAddressesRequest>,
+) -> Result<Json<SuccessResponse<DeriveAddressesResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    validation.finish()?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: DeriveAddressesResponse {
+            classic_ata: get_associated_token_address_with_program_id(&owner_pubkey, &mint_pubkey, &spl_token::ID)
+                .to_string(),
+            token_2022_ata: get_associated_token_address_with_program_id(
+                &owner_pubkey,
+                &mint_pubkey,
+                &spl_token_2022::ID,
+            )
+            .to_string(),
+        },
+    }))
+}
+
+/// Checks that `ata` is the canonical associated token account for `owner` and
+/// `mint`, rather than some other account a client was handed.
+async fn verify_ata(
+    Json(req): Json<VerifyAtaRequest>,
+) -> Result<Json<SuccessResponse<VerifyAtaResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let ata_pubkey = validation.pubkey("ata", &req.ata, "Invalid ATA public key");
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let program_id = validation.token_program("program", req.program.as_deref());
+    validation.finish()?;
+
+    let expected = get_associated_token_address_with_program_id(&owner_pubkey, &mint_pubkey, &program_id);
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: VerifyAtaResponse {
+            valid: expected == ata_pubkey,
+            expected: expected.to_string(),
+        },
+    }))
+}
+
+/// Initializes a Token-2022 account with the immutable-owner extension, so its
+/// owner can never be reassigned.
+async fn initialize_account_2022(
+    Json(req): Json<InitializeAccount2022Request>,
+) -> Result<Json<SuccessResponse<AccountSetupResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let account_pubkey = validation.pubkey("account", &req.account, "Invalid account public key");
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    validation.finish()?;
+
+    let instructions = vec![
+        spl_token_2022::instruction::initialize_immutable_owner(&spl_token_2022::ID, &account_pubkey)
+            .map_err(instruction_error)?,
+        spl_token_2022::instruction::initialize_account3(
+            &spl_token_2022::ID,
+            &account_pubkey,
+            &mint_pubkey,
+            &owner_pubkey,
+        )
+        .map_err(instruction_error)?,
+    ];
+    let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Account>(&[
+        ExtensionType::ImmutableOwner,
+    ])
+    .map_err(instruction_error)?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: AccountSetupResponse {
+            instructions: instructions.into_iter().map(SerializableInstruction::from).collect(),
+            space,
+        },
+    }))
+}
+
+/// Builds a token-metadata `update_field` for a Token-2022 mint that stores its
+/// metadata in the mint account itself, the layout `/token/create` sets up when
+/// the metadata pointer targets the mint.
+async fn update_token_metadata(
+    Json(req): Json<UpdateMetadataRequest>,
+) -> Result<Json<SuccessResponse<SerializableInstruction>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    let authority_pubkey = validation.pubkey("authority", &req.authority, "Invalid authority public key");
+    if req.field.is_empty() {
+        validation.fail("field", "must not be empty", "Metadata field is required");
+    }
+    if req.value.len() > MAX_METADATA_VALUE_LEN {
+        validation.fail(
+            "value",
+            &format!("must be at most {} bytes", MAX_METADATA_VALUE_LEN),
+            "Metadata value is too long",
+        );
+    }
+    validation.finish()?;
+
+    let field = match req.field.as_str() {
+        "name" => Field::Name,
+        "symbol" => Field::Symbol,
+        "uri" => Field::Uri,
+        key => Field::Key(key.to_string()),
+    };
+    let instruction = spl_token_metadata_interface::instruction::update_field(
+        &spl_token_2022::ID,
+        &mint_pubkey,
+        &authority_pubkey,
+        field,
+        req.value,
+    );
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: SerializableInstruction::from(instruction),
+    }))
+}
+
+impl Operation {
+    fn build(&self, state: &AppState) -> Result<Vec<Instruction>, (StatusCode, Json<ErrorResponse>)> {
+        match self {
+            Operation::CreateToken(CreateTokenRequest { include_program_accounts: true, .. })
+            | Operation::MintTo(MintTokenRequest { include_program_accounts: true, .. })
+            | Operation::TransferSol(SendSolRequest { include_program_accounts: true, .. })
+            | Operation::TransferToken(SendTokenRequest { include_program_accounts: true, .. }) => Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new(
+                    "includeProgramAccounts is not supported in operations; compiled messages list every program",
+                )),
+            )),
+            Operation::CreateToken(CreateTokenRequest { include_compute_hint: true, .. })
+            | Operation::MintTo(MintTokenRequest { include_compute_hint: true, .. })
+            | Operation::TransferSol(SendSolRequest { include_compute_hint: true, .. })
+            | Operation::TransferToken(SendTokenRequest { include_compute_hint: true, .. }) => Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new(
+                    "includeComputeHint is not supported in operations; it applies to single-instruction endpoints",
+                )),
+            )),
+            Operation::CreateToken(req) => build_create_token(req, state.default_decimals).map(|setup| setup.instructions),
+            Operation::MintTo(req) => build_mint_to(req).map(|ix| vec![ix]),
+            Operation::TransferSol(req) if req.ensure_rent_exempt => Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new("ensureRentExempt is only supported by /send/sol")),
+            )),
+            Operation::TransferSol(req) => build_send_sol(req).map(|ix| vec![ix]),
+            Operation::TransferToken(req) => build_send_token(req).map(|ix| vec![ix]),
+            Operation::WrapSol(req) => build_wrap_sol(req).map(|(_, ixs)| ixs),
+            Operation::UnwrapSol(req) => build_unwrap_sol(req).map(|(_, ixs)| ixs),
+        }
+    }
+}
+
+async fn transaction_size(
+    Json(req): Json<TransactionSizeRequest>,
+) -> Result<Json<SuccessResponse<TransactionSizeResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let transaction = match (req.transaction.as_deref(), req.message.as_deref()) {
+        (Some(transaction), None) => decode_transaction(transaction)?,
+        (None, Some(message)) => {
+            let message = decode_message(message)?;
+            VersionedTransaction {
+                signatures: vec![Signature::default(); message.header().num_required_signatures as usize],
+                message,
+            }
+        }
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse::new("Provide exactly one of transaction or message")),
+            ))
+        }
+    };
+    let bytes = bincode::serialized_size(&transaction).expect("transaction serialization is infallible") as usize;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: TransactionSizeResponse {
+            bytes,
+            limit: PACKET_DATA_SIZE,
+            fits: bytes <= PACKET_DATA_SIZE,
+        },
+    }))
+}
+
+/// Parses the instruction into a real `Instruction` and serializes it again,
+/// which normalizes it and checks that the round trip loses nothing.
+async fn rebuild_instruction(
+    Json(req): Json<SerializableInstruction>,
+) -> Result<Json<SuccessResponse<SerializableInstruction>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let instruction = req.to_instruction("", &mut validation);
+    validation.finish()?;
+
+    let rebuilt = SerializableInstruction::from(instruction);
+    if rebuilt != req {
+        tracing::error!(program_id = %req.program_id, "instruction did not survive a serialization round trip");
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse::new("Rebuilt instruction differs from the input").with_code("ROUND_TRIP_MISMATCH")),
+        ));
+    }
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: rebuilt,
+    }))
+}
+
+/// Builds an arbitrary program's instruction from caller-chosen account metas,
+/// so custom program calls get the same validation and output shape as the
+/// built-in ones. `withIndices` lays the accounts out against a message's
+/// account keys, to debug "account index out of bounds" failures.
+async fn custom_instruction(
+    Json(req): Json<CustomInstructionRequest>,
+) -> Result<Json<SuccessResponse<CustomInstructionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let program_id = validation.pubkey("programId", &req.program_id, "Invalid program id");
+    let accounts = req
+        .accounts
+        .iter()
+        .enumerate()
+        .map(|(i, meta)| AccountMeta {
+            pubkey: validation.pubkey(
+                &format!("accounts[{}].pubkey", i),
+                &meta.pubkey,
+                "Invalid instruction account public key",
+            ),
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        })
+        .collect();
+    let data = general_purpose::STANDARD.decode(&req.data).unwrap_or_else(|_| {
+        validation.fail("data", "must be base64", "Invalid instruction data; must be base64");
+        Vec::new()
+    });
+    let account_keys: Option<Vec<Pubkey>> = match (req.with_indices, &req.account_keys) {
+        (true, None) => {
+            validation.fail("accountKeys", "is required with withIndices", "accountKeys is required with withIndices");
+            None
+        }
+        (true, Some(keys)) => Some(
+            keys.iter()
+                .enumerate()
+                .map(|(i, key)| validation.pubkey(&format!("accountKeys[{}]", i), key, "Invalid account key"))
+                .collect(),
+        ),
+        (false, Some(_)) => {
+            validation.fail("accountKeys", "is only used with withIndices", "accountKeys requires withIndices");
+            None
+        }
+        (false, None) => None,
+    };
+    validation.finish()?;
+
+    let instruction = Instruction { program_id, accounts, data };
+    let position = |pubkey: &Pubkey| account_keys.as_ref().and_then(|keys| keys.iter().position(|key| key == pubkey));
+    let indexed_accounts = account_keys.as_ref().map(|_| {
+        instruction
+            .accounts
+            .iter()
+            .map(|meta| IndexedAccountMeta {
+                meta: SerializableAccountMeta::from(meta.clone()),
+                index: position(&meta.pubkey),
+            })
+            .collect()
+    });
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: CustomInstructionResponse {
+            program_id_index: account_keys.as_ref().map(|_| position(&instruction.program_id)),
+            indexed_accounts,
+            instruction: instruction.into(),
+        },
+    }))
+}
+
+/// JSON Schema's name for a field's type, narrowed to the Rust integer width
+/// where there is one; `object` for nested structures.
+fn schema_type_name(property: &Value) -> String {
+    if let Some(format) = property["format"].as_str() {
+        return format.replace("uint", "u").replace("int", "i");
+    }
+    let types = match &property["type"] {
+        Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+        Value::String(name) => vec![name.as_str()],
+        _ => Vec::new(),
+    };
+    match types.into_iter().find(|name| *name != "null") {
+        Some("boolean") => "bool".to_string(),
+        Some(name) => name.to_string(),
+        None => "object".to_string(),
+    }
+}
+
+/// Lists what `/instructions/build` accepts, read off the JSON schema derived
+/// from `Operation` itself so the two cannot drift apart.
+async fn list_operations() -> Json<SuccessResponse<OperationsResponse>> {
+    let schema = schemars::schema_for!(Operation).to_value();
+    let operations = schema["oneOf"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|variant| {
+            let definition = variant["$ref"]
+                .as_str()
+                .and_then(|reference| schema.pointer(reference.trim_start_matches('#')))
+                .unwrap_or(&Value::Null);
+            let required: Vec<&str> =
+                definition["required"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+            let mut fields: Vec<OperationField> = definition["properties"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(name, property)| OperationField {
+                    name: name.clone(),
+                    field_type: schema_type_name(property),
+                    required: required.contains(&name.as_str()),
+                    description: property["description"].as_str().map(|text| text.replace('\n', " ")),
+                })
+                .collect();
+            fields.sort_by_key(|field| !field.required);
+            OperationSchema {
+                name: variant["properties"]["type"]["const"].as_str().unwrap_or_default().to_string(),
+                programs: variant["programs"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|program| program.as_str().map(str::to_string))
+                    .collect(),
+                fields,
+            }
+        })
+        .collect();
+
+    Json(SuccessResponse {
+        success: true,
+        data: OperationsResponse { operations },
+    })
+}
+
+/// Builds every operation in order, stopping at the first one that fails and
+/// reporting its index.
+async fn build_instructions(
+    State(state): State<AppState>,
+    Json(req): Json<BuildInstructionsRequest>,
+) -> Result<Json<SuccessResponse<InstructionListResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    if req.operations.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("At least one operation is required"))));
+    }
+
+    let instructions = build_operations(&req.operations, &state)?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: InstructionListResponse {
+            instructions: instructions.into_iter().map(SerializableInstruction::from).collect(),
+        },
+    }))
 }
 
-impl From<Instruction> for SerializableInstruction {
-    fn from(instruction: Instruction) -> Self {
-        SerializableInstruction {
-            program_id: instruction.program_id.to_string(),
-            accounts: instruction
-                .accounts
-                .into_iter()
-                .map(SerializableAccountMeta::from)
-                .collect(),
-            instruction_data: general_purpose::STANDARD.encode(&instruction.data),
+/// Builds each operation in turn, prefixing the first failure's message and
+/// fields with `operations[i]`.
+fn build_operations(
+    operations: &[Operation],
+    state: &AppState,
+) -> Result<Vec<Instruction>, (StatusCode, Json<ErrorResponse>)> {
+    let mut instructions = Vec::new();
+    for (index, operation) in operations.iter().enumerate() {
+        match operation.build(state) {
+            Ok(built) => instructions.extend(built),
+            Err((status, Json(mut error))) => {
+                error.error = format!("operations[{}]: {}", index, error.error);
+                error.fields = error.fields.map(|fields| {
+                    fields
+                        .into_iter()
+                        .map(|(field, detail)| (format!("operations[{}].{}", index, field), detail))
+                        .collect()
+                });
+                return Err((status, Json(error)));
+            }
         }
     }
+    Ok(instructions)
 }
 
-impl From<AccountMeta> for SerializableAccountMeta {
-    fn from(meta: AccountMeta) -> Self {
-        SerializableAccountMeta {
-            pubkey: meta.pubkey.to_string(),
-            is_signer: meta.is_signer,
-            is_writable: meta.is_writable,
+/// Tells clients whether the ATA still needs creating and what it will cost.
+async fn ata_status(
+    State(state): State<AppState>,
+    Json(req): Json<AtaStatusRequest>,
+) -> Result<Json<SuccessResponse<AtaStatusResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    validation.finish()?;
+
+    let address = get_associated_token_address(&owner_pubkey, &mint_pubkey);
+    let (exists, rent_lamports) = match state.rpc.as_deref() {
+        Some(rpc) => {
+            let (account, rent) = tokio::try_join!(
+                rpc.get_account_with_commitment(&address, rpc.commitment()),
+                rpc.get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN),
+            )
+            .map_err(rpc_error)?;
+            (Some(account.value.is_some()), rent)
         }
+        None => (None, Rent::default().minimum_balance(spl_token::state::Account::LEN)),
+    };
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: AtaStatusResponse {
+            address: address.to_string(),
+            exists,
+            rent_lamports,
+        },
+    }))
+}
+
+/// Fetches every ATA in one `getMultipleAccounts` call.
+async fn token_balances(
+    State(state): State<AppState>,
+    Json(req): Json<TokenBalancesRequest>,
+) -> Result<Json<SuccessResponse<TokenBalancesResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let owner_pubkey = validation.pubkey("owner", &req.owner, "Invalid owner public key");
+    let program_id = validation.token_program("program", req.program.as_deref());
+    if req.mints.is_empty() {
+        validation.fail("mints", "must not be empty", "At least one mint is required");
     }
+    validation.batch_limit("mints", req.mints.len(), state.batch_limits.accounts, "mints");
+    let mints: Vec<Pubkey> = req
+        .mints
+        .iter()
+        .enumerate()
+        .map(|(i, mint)| validation.pubkey(&format!("mints[{}]", i), mint, "Invalid mint public key"))
+        .collect();
+    validation.finish()?;
+
+    let atas: Vec<Pubkey> = mints
+        .iter()
+        .map(|mint| get_associated_token_address_with_program_id(&owner_pubkey, mint, &program_id))
+        .collect();
+    let rpc = rpc_client(&state)?;
+    let accounts = rpc
+        .get_multiple_accounts_with_commitment(&atas, rpc.commitment())
+        .await
+        .map_err(rpc_error)?
+        .value;
+
+    let balances = mints
+        .iter()
+        .zip(&atas)
+        .zip(accounts)
+        .map(|((mint, ata), account)| {
+            let amount = account
+                .as_ref()
+                .filter(|account| account.owner == program_id)
+                .and_then(|account| StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data).ok())
+                .map_or(0, |state| state.base.amount);
+            TokenBalance {
+                mint: mint.to_string(),
+                ata: ata.to_string(),
+                amount,
+                exists: account.is_some(),
+            }
+        })
+        .collect();
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: TokenBalancesResponse { balances },
+    }))
 }
 
+/// Native balances of many accounts in one `getMultipleAccounts` call.
+async fn sol_balances(
+    State(state): State<AppState>,
+    Json(req): Json<SolBalancesRequest>,
+) -> Result<Json<SuccessResponse<SolBalancesResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if req.pubkeys.is_empty() {
+        validation.fail("pubkeys", "must not be empty", "At least one public key is required");
+    }
+    validation.batch_limit("pubkeys", req.pubkeys.len(), state.batch_limits.accounts, "public keys");
+    let pubkeys: Vec<Pubkey> = req
+        .pubkeys
+        .iter()
+        .enumerate()
+        .map(|(i, pubkey)| validation.pubkey(&format!("pubkeys[{}]", i), pubkey, "Invalid public key"))
+        .collect();
+    validation.finish()?;
 
-async fn generate_keypair() -> Result<Json<SuccessResponse<KeypairResponse>>, (StatusCode, Json<ErrorResponse>)> {
-    let keypair = Keypair::new();
-    let response = SuccessResponse {
+    let rpc = rpc_client(&state)?;
+    let accounts = rpc
+        .get_multiple_accounts_with_commitment(&pubkeys, rpc.commitment())
+        .await
+        .map_err(rpc_error)?
+        .value;
+
+    let balances = pubkeys
+        .iter()
+        .zip(accounts)
+        .map(|(pubkey, account)| SolBalance {
+            pubkey: pubkey.to_string(),
+            lamports: account.as_ref().map_or(0, |account| account.lamports),
+            exists: account.is_some(),
+        })
+        .collect();
+
+    Ok(Json(SuccessResponse {
         success: true,
-        data: KeypairResponse {
-            pubkey: keypair.pubkey().to_string(),
-            secret: keypair.to_base58_string(),
+        data: SolBalancesResponse { balances },
+    }))
+}
+
+/// Guesses what an account is from its owner program and data layout.
+fn account_kind(account: &Account) -> &'static str {
+    // Token-2022 accounts with extensions are padded past the multisig size and
+    // carry their account type in the byte right after the base account.
+    let token_2022_kind = || match account.data.len() {
+        spl_token_2022::state::Mint::LEN => "Token2022Mint",
+        spl_token_2022::state::Account::LEN => "Token2022Account",
+        spl_token_2022::state::Multisig::LEN => "TokenMultisig",
+        _ => match account.data.get(spl_token_2022::state::Account::LEN) {
+            Some(1) => "Token2022Mint",
+            Some(2) => "Token2022Account",
+            _ => "Unknown",
         },
     };
-    Ok(Json(response))
+    if account.executable {
+        return "ProgramAccount";
+    }
+    match account.owner {
+        owner if owner == solana_system_interface::program::ID => match account.data.len() {
+            0 => "SystemAccount",
+            80 => "NonceAccount",
+            _ => "Unknown",
+        },
+        owner if owner == spl_token::ID => match account.data.len() {
+            spl_token::state::Mint::LEN => "TokenMint",
+            spl_token::state::Account::LEN => "TokenAccount",
+            spl_token::state::Multisig::LEN => "TokenMultisig",
+            _ => "Unknown",
+        },
+        owner if owner == spl_token_2022::ID => token_2022_kind(),
+        owner if owner == UPGRADEABLE_LOADER_ID => "ProgramData",
+        owner if owner == STAKE_PROGRAM_ID => "StakeAccount",
+        owner if owner == VOTE_PROGRAM_ID => "VoteAccount",
+        owner if owner == SYSVAR_OWNER_ID => "Sysvar",
+        _ => "Unknown",
+    }
 }
 
-async fn create_token(
-    Json(req): Json<CreateTokenRequest>,
-) -> Result<Json<SuccessResponse<SerializableInstruction>>, (StatusCode, Json<ErrorResponse>)> {
-    let mint_authority_pubkey = match Pubkey::from_str(&req.mint_authority) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new("Invalid mint authority public key")),
-            ))
-        }
-    };
-    let mint_pubkey = match Pubkey::from_str(&req.mint) {
-        Ok(pk) => pk,
-        Err(_) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid mint public key")))),
+async fn classify_account(
+    State(state): State<AppState>,
+    Json(req): Json<ClassifyAccountRequest>,
+) -> Result<Json<SuccessResponse<ClassifyAccountResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let account_pubkey = validation.pubkey("account", &req.account, "Invalid account public key");
+    validation.finish()?;
+
+    let rpc = rpc_client(&state)?;
+    let account = rpc
+        .get_account_with_commitment(&account_pubkey, rpc.commitment())
+        .await
+        .map_err(rpc_error)?
+        .value;
+
+    let data = match account {
+        None => ClassifyAccountResponse {
+            exists: false,
+            classification: "NotFound",
+            owner: None,
+            data_length: None,
+            lamports: None,
+        },
+        Some(account) => ClassifyAccountResponse {
+            exists: true,
+            classification: account_kind(&account),
+            owner: Some(account.owner.to_string()),
+            data_length: Some(account.data.len()),
+            lamports: Some(account.lamports),
+        },
     };
 
-    match spl_token::instruction::initialize_mint(
-        &spl_token::ID,
-        &mint_pubkey,
-        &mint_authority_pubkey,
-        None,
-        req.decimals,
-    ) {
-        Ok(instruction) => {
-            let serializable_instruction: SerializableInstruction = instruction.into();
-            Ok(Json(SuccessResponse {
-                success: true,
-                data: serializable_instruction,
-            }))
-        }
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(&format!("Failed to create instruction: {}", e))),
-        )),
-    }
+    Ok(Json(SuccessResponse { success: true, data }))
 }
 
-async fn mint_token(
-    Json(req): Json<MintTokenRequest>,
-) -> Result<Json<SuccessResponse<SerializableInstruction>>, (StatusCode, Json<ErrorResponse>)> {
-    let mint_pubkey = match Pubkey::from_str(&req.mint) {
-        Ok(pk) => pk,
-        Err(_) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid mint public key")))),
-    };
-    let destination_pubkey = match Pubkey::from_str(&req.destination) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new("Invalid destination public key")),
-            ))
-        }
-    };
-    let authority_pubkey = match Pubkey::from_str(&req.authority) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new("Invalid authority public key")),
-            ))
-        }
+async fn transaction_status(
+    State(state): State<AppState>,
+    Json(req): Json<TransactionStatusRequest>,
+) -> Result<Json<SuccessResponse<TransactionStatusResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let signature = Signature::from_str(&req.signature).unwrap_or_else(|_| {
+        validation.fail("signature", "invalid signature", "Invalid transaction signature; must be base58");
+        Signature::default()
+    });
+    validation.finish()?;
+
+    let rpc = rpc_client(&state)?;
+    let status = rpc
+        .get_signature_statuses(&[signature])
+        .await
+        .map_err(rpc_error)?
+        .value
+        .into_iter()
+        .next()
+        .flatten();
+
+    let data = match status {
+        None => TransactionStatusResponse { status: "notFound", slot: None, confirmations: None, error: None },
+        Some(status) => TransactionStatusResponse {
+            status: match (&status.err, status.confirmation_status()) {
+                (Some(_), _) => "failed",
+                (None, TransactionConfirmationStatus::Processed) => "processed",
+                (None, TransactionConfirmationStatus::Confirmed) => "confirmed",
+                (None, TransactionConfirmationStatus::Finalized) => "finalized",
+            },
+            slot: Some(status.slot),
+            confirmations: status.confirmations,
+            error: status.err.map(|err| err.to_string()),
+        },
     };
 
-    match spl_token::instruction::mint_to(
-        &spl_token::ID,
-        &mint_pubkey,
-        &destination_pubkey,
-        &authority_pubkey,
-        &[],
-        req.amount,
-    ) {
-        Ok(instruction) => {
-            let serializable_instruction: SerializableInstruction = instruction.into();
-            Ok(Json(SuccessResponse {
-                success: true,
-                data: serializable_instruction,
-            }))
+    Ok(Json(SuccessResponse { success: true, data }))
+}
+
+/// Expands compiled instructions back into full account metas, appending the
+/// addresses a v0 transaction loaded from lookup tables after its static keys.
+fn expand_instructions(message: &VersionedMessage, loaded: Option<UiLoadedAddresses>) -> Option<Vec<SerializableInstruction>> {
+    let static_keys = message.static_account_keys();
+    let (writable, readonly) = loaded.map(|loaded| (loaded.writable, loaded.readonly)).unwrap_or_default();
+    let keys: Vec<String> = static_keys
+        .iter()
+        .map(Pubkey::to_string)
+        .chain(writable.iter().cloned())
+        .chain(readonly)
+        .collect();
+    let writable_end = static_keys.len() + writable.len();
+    let is_writable = |index: usize| {
+        if index < static_keys.len() {
+            message.is_maybe_writable(index, None)
+        } else {
+            index < writable_end
         }
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(&format!("Failed to create instruction: {}", e))),
-        )),
-    }
+    };
+    message
+        .instructions()
+        .iter()
+        .map(|instruction| {
+            Some(SerializableInstruction {
+                program_id: keys.get(instruction.program_id_index as usize)?.clone(),
+                accounts: instruction
+                    .accounts
+                    .iter()
+                    .map(|&index| {
+                        let index = index as usize;
+                        Some(SerializableAccountMeta {
+                            pubkey: keys.get(index)?.clone(),
+                            is_signer: message.is_signer(index),
+                            is_writable: is_writable(index),
+                        })
+                    })
+                    .collect::<Option<_>>()?,
+                instruction_data: general_purpose::STANDARD.encode(&instruction.data),
+            })
+        })
+        .collect()
 }
 
-async fn sign_message(
-    Json(req): Json<SignMessageRequest>,
-) -> Result<Json<SuccessResponse<SignMessageResponse>>, (StatusCode, Json<ErrorResponse>)> {
-    if req.message.is_empty() || req.secret.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Missing required fields"))));
-    }
+/// Fetches a transaction by signature for auditing. `getTransaction` refuses
+/// `processed`, so the lookup uses at least `confirmed`.
+async fn transaction_get(
+    State(state): State<AppState>,
+    Json(req): Json<TransactionGetRequest>,
+) -> Result<Json<SuccessResponse<TransactionGetResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let signature = Signature::from_str(&req.signature).unwrap_or_else(|_| {
+        validation.fail("signature", "invalid signature", "Invalid transaction signature; must be base58");
+        Signature::default()
+    });
+    let raw = match req.encoding.as_deref() {
+        None | Some("json") => false,
+        Some("base64") => true,
+        Some(_) => {
+            validation.fail("encoding", "must be json or base64", "Invalid encoding; must be json or base64");
+            false
+        }
+    };
+    validation.finish()?;
 
-    let keypair = match bs58::decode(&req.secret).into_vec() {
-        Ok(bytes) => match Keypair::try_from(&bytes) {
-            Ok(kp) => kp,
-            Err(_) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid secret key")))),
-        },
-        Err(_) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid secret key format")))),
+    let rpc = rpc_client(&state)?;
+    let commitment = if rpc.commitment().is_at_least_confirmed() {
+        rpc.commitment()
+    } else {
+        CommitmentConfig::confirmed()
     };
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(commitment),
+        max_supported_transaction_version: Some(0),
+    };
+    let signatures = [signature];
+    let (fetched, statuses) = tokio::try_join!(
+        rpc.send::<Option<EncodedConfirmedTransactionWithStatusMeta>>(
+            RpcRequest::GetTransaction,
+            json!([signature.to_string(), config]),
+        ),
+        rpc.get_signature_statuses(&signatures),
+    )
+    .map_err(rpc_error)?;
+    let status = statuses.value.into_iter().next().flatten();
 
-    let signature = keypair.sign_message(req.message.as_bytes());
+    let not_found = |status| TransactionGetResponse {
+        status,
+        slot: None,
+        block_time: None,
+        fee: None,
+        error: None,
+        instructions: None,
+        transaction: None,
+    };
+    let Some(fetched) = fetched else {
+        let data = not_found(if status.is_some() { "pending" } else { "notFound" });
+        return Ok(Json(SuccessResponse { success: true, data }));
+    };
+    let transaction = fetched.transaction.transaction.decode().ok_or((
+        StatusCode::BAD_GATEWAY,
+        Json(ErrorResponse::new("RPC returned an undecodable transaction")),
+    ))?;
+    let encoded = if raw {
+        let bytes = bincode::serialize(&transaction).map_err(|e| {
+            tracing::error!(error = %e, "failed to re-serialize a fetched transaction");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse::new("Failed to serialize transaction")),
+            )
+        })?;
+        Some(general_purpose::STANDARD.encode(bytes))
+    } else {
+        None
+    };
+    let meta = fetched.transaction.meta;
+    let instructions = if raw {
+        None
+    } else {
+        let loaded = meta.as_ref().and_then(|meta| Option::<UiLoadedAddresses>::from(meta.loaded_addresses.clone()));
+        Some(expand_instructions(&transaction.message, loaded).ok_or((
+            StatusCode::BAD_GATEWAY,
+            Json(ErrorResponse::new("RPC returned a transaction with out-of-range account indexes")),
+        ))?)
+    };
 
     Ok(Json(SuccessResponse {
         success: true,
-        data: SignMessageResponse {
-            signature: general_purpose::STANDARD.encode(signature.as_ref()),
-            public_key: keypair.pubkey().to_string(),
-            message: req.message,
+        data: TransactionGetResponse {
+            // Statuses age out of the cluster's cache once rooted.
+            status: match status.and_then(|status| status.confirmation_status) {
+                Some(TransactionConfirmationStatus::Finalized) | None => "finalized",
+                Some(_) => "confirmed",
+            },
+            slot: Some(fetched.slot),
+            block_time: fetched.block_time,
+            fee: meta.as_ref().map(|meta| meta.fee),
+            error: meta.and_then(|meta| meta.err).map(|err| err.to_string()),
+            instructions,
+            transaction: encoded,
         },
     }))
 }
 
-async fn verify_message(
-    Json(req): Json<VerifyMessageRequest>,
-) -> Result<Json<SuccessResponse<VerifyMessageResponse>>, (StatusCode, Json<ErrorResponse>)> {
-    let pubkey = match Pubkey::from_str(&req.pubkey) {
-        Ok(pk) => pk,
-        Err(_) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid public key")))),
+async fn token_account_info(
+    State(state): State<AppState>,
+    Json(req): Json<TokenAccountInfoRequest>,
+) -> Result<Json<SuccessResponse<TokenAccountInfoResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let account_pubkey = validation.pubkey("account", &req.account, "Invalid account public key");
+    validation.finish()?;
+
+    let rpc = rpc_client(&state)?;
+    let account = match state.account_cache.get(rpc, &account_pubkey).await.map_err(rpc_error)? {
+        Some(account) => account,
+        None => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::new("Account not found")))),
     };
 
-    let signature_bytes = match general_purpose::STANDARD.decode(&req.signature) {
-        Ok(bytes) => bytes,
-        Err(_) => {
+    let token_account = match spl_token::state::Account::unpack(&account.data) {
+        Ok(token_account) if account.owner == spl_token::ID => token_account,
+        _ => {
             return Err((
                 StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new("Invalid signature format; must be base64")),
+                Json(ErrorResponse::new("Account is not an SPL token account")),
             ))
         }
     };
 
-    let signature = match Signature::try_from(signature_bytes.as_slice()) {
-        Ok(sig) => sig,
-        Err(_) => {
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: TokenAccountInfoResponse {
+            mint: token_account.mint.to_string(),
+            owner: token_account.owner.to_string(),
+            amount: token_account.amount,
+            delegate: Option::<Pubkey>::from(token_account.delegate).map(|pk| pk.to_string()),
+            state: match token_account.state {
+                AccountState::Uninitialized => "uninitialized",
+                AccountState::Initialized => "initialized",
+                AccountState::Frozen => "frozen",
+            },
+        },
+    }))
+}
+
+async fn mint_info(
+    State(state): State<AppState>,
+    Json(req): Json<MintInfoRequest>,
+) -> Result<Json<SuccessResponse<MintInfoResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    validation.finish()?;
+
+    let rpc = rpc_client(&state)?;
+    let account = match state.mint_cache.get(rpc, &mint_pubkey).await.map_err(rpc_error)? {
+        Some(account) => account,
+        None => return Err((StatusCode::NOT_FOUND, Json(ErrorResponse::new("Mint account not found")))),
+    };
+
+    // `unpack_unchecked` so an uninitialized mint is reported rather than rejected.
+    let mint = match spl_token::state::Mint::unpack_unchecked(&account.data) {
+        Ok(mint) if account.owner == spl_token::ID => mint,
+        _ => {
             return Err((
                 StatusCode::BAD_REQUEST,
-                Json(ErrorResponse::new("Invalid signature length")),
+                Json(ErrorResponse::new("Account is not an SPL token mint")),
             ))
         }
     };
 
-    let valid = signature.verify(pubkey.as_ref(), req.message.as_bytes());
-
     Ok(Json(SuccessResponse {
         success: true,
-        data: VerifyMessageResponse {
-            valid,
-            message: req.message,
-            pubkey: req.pubkey,
+        data: MintInfoResponse {
+            decimals: mint.decimals,
+            supply: mint.supply,
+            mint_authority: Option::<Pubkey>::from(mint.mint_authority).map(|pk| pk.to_string()),
+            freeze_authority: Option::<Pubkey>::from(mint.freeze_authority).map(|pk| pk.to_string()),
+            is_initialized: mint.is_initialized,
         },
     }))
 }
 
-async fn send_sol(
-    Json(req): Json<SendSolRequest>,
-) -> Result<Json<SuccessResponse<SerializableInstruction>>, (StatusCode, Json<ErrorResponse>)> {
-    let from_pubkey = match Pubkey::from_str(&req.from) {
-        Ok(pk) => pk,
-        Err(_) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid 'from' public key")))),
-    };
-    let to_pubkey = match Pubkey::from_str(&req.to) {
-        Ok(pk) => pk,
-        Err(_) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid 'to' public key")))),
+/// Current supply of a Token or Token-2022 mint. Read straight from RPC rather
+/// than through `mint_cache`, since supply moves with every mint and burn.
+async fn token_supply(
+    State(state): State<AppState>,
+    Json(req): Json<TokenSupplyRequest>,
+) -> Result<Json<SuccessResponse<TokenSupplyResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    let mint_pubkey = validation.pubkey("mint", &req.mint, "Invalid mint public key");
+    validation.finish()?;
+
+    let rpc = rpc_client(&state)?;
+    let account = rpc
+        .get_account_with_commitment(&mint_pubkey, rpc.commitment())
+        .await
+        .map_err(rpc_error)?
+        .value
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ErrorResponse::new("Mint account not found"))))?;
+
+    let program = match account.owner {
+        owner if owner == spl_token::ID => "token",
+        owner if owner == spl_token_2022::ID => "token2022",
+        _ => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Account is not an SPL token mint")))),
     };
+    // Token-2022 mints keep the base layout in front of any extensions.
+    let mint = account
+        .data
+        .get(..spl_token_2022::state::Mint::LEN)
+        .and_then(|data| spl_token_2022::state::Mint::unpack_unchecked(data).ok())
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Account is not an SPL token mint"))))?;
+    if !mint.is_initialized {
+        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Mint is not initialized"))));
+    }
 
-    if from_pubkey == to_pubkey {
-        return Err((
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: TokenSupplyResponse {
+            supply: mint.supply,
+            decimals: mint.decimals,
+            ui_supply: spl_token::amount_to_ui_amount_string_trimmed(mint.supply, mint.decimals),
+            program,
+        },
+    }))
+}
+
+/// Maps a program's instruction-builder error to a 400.
+fn instruction_error(e: ProgramError) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse::new(&format!("Failed to create instruction: {}", e))),
+    )
+}
+
+/// Decodes caller-supplied base64 instruction data, answering 400 when it is not base64.
+fn decode_instruction_data(encoded: &str) -> Result<Vec<u8>, (StatusCode, Json<ErrorResponse>)> {
+    general_purpose::STANDARD.decode(encoded).map_err(|_| {
+        (
             StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new("Sender and recipient addresses cannot be the same.")),
-        ));
+            Json(ErrorResponse::new("Invalid instruction data; must be base64")),
+        )
+    })
+}
+
+/// Rejects state-changing endpoints while the service is in read-only mode.
+/// Pure instruction builders never call this.
+fn require_writable(state: &AppState) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    if !state.read_only {
+        return Ok(());
     }
-    if req.lamports == 0 {
-        return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Cannot send 0 lamports."))));
+    Err((
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ErrorResponse::new("Service in read-only mode").with_code("READ_ONLY")),
+    ))
+}
+
+fn require_server_signing(state: &AppState) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    require_writable(state)?;
+    if state.server_signing {
+        return Ok(());
     }
+    Err((
+        StatusCode::FORBIDDEN,
+        Json(ErrorResponse::new("Server-side signing is disabled").with_code("SERVER_SIGNING_DISABLED")),
+    ))
+}
+
+/// Signs a client-built message with a client-supplied secret key.
+///
+/// This moves private keys across the wire and into this process, so it is
+/// disabled unless `ENABLE_SERVER_SIGNING` is set, and should only be enabled
+/// for custodial deployments behind TLS that have explicitly opted in.
+async fn sign_transaction(
+    State(state): State<AppState>,
+    Json(req): Json<SignTransactionRequest>,
+) -> Result<Json<SuccessResponse<SignTransactionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    require_server_signing(&state)?;
+
+    let message = decode_message(&req.transaction)?;
+    let keypair = decode_keypair(&req.secret)?;
+    tracing::warn!(signer = %keypair.pubkey(), "signing transaction with a client-supplied secret key");
 
-    let instruction = system_instruction::transfer(&from_pubkey, &to_pubkey, req.lamports);
-    let serializable_instruction: SerializableInstruction = instruction.into();
+    let mut transaction = VersionedTransaction {
+        signatures: Vec::new(),
+        message,
+    };
+    sign_transaction_slot(&mut transaction, &keypair)?;
 
     Ok(Json(SuccessResponse {
         success: true,
-        data: serializable_instruction,
+        data: SignTransactionResponse::from(transaction),
     }))
 }
 
-async fn send_token(
-    Json(req): Json<SendTokenRequest>,
-) -> Result<Json<SuccessResponse<SerializableInstruction>>, (StatusCode, Json<ErrorResponse>)> {
-    let destination_pubkey = match Pubkey::from_str(&req.destination) {
-        Ok(pk) => pk,
-        Err(_) => {
-            return Err((
-                Status,tusCode::BAD_REQUEST,
-                Json(ErrorResponse::new("Invalid destination public key")),
-            ))
-        }
+/// Adds one signature to an existing, possibly partially signed, transaction so
+/// several parties can sign in turn. Gated by `ENABLE_SERVER_SIGNING` like `/sign`.
+async fn sign_transaction_partial(
+    State(state): State<AppState>,
+    Json(req): Json<SignTransactionRequest>,
+) -> Result<Json<SuccessResponse<SignTransactionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    require_server_signing(&state)?;
+
+    let mut transaction = decode_transaction(&req.transaction)?;
+    let keypair = decode_keypair(&req.secret)?;
+    tracing::warn!(signer = %keypair.pubkey(), "partially signing transaction with a client-supplied secret key");
+
+    sign_transaction_slot(&mut transaction, &keypair)?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: SignTransactionResponse::from(transaction),
+    }))
+}
+
+async fn decode_token_instruction(
+    Json(req): Json<DecodeInstructionRequest>,
+) -> Result<Json<SuccessResponse<DecodedInstructionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let data = decode_instruction_data(&req.data)?;
+    let instruction = TokenInstruction::unpack(&data).map_err(|_| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("Data is not a valid SPL token instruction")),
+        )
+    })?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: describe_token_instruction(instruction),
+    }))
+}
+
+async fn decode_system_instruction(
+    Json(req): Json<DecodeInstructionRequest>,
+) -> Result<Json<SuccessResponse<DecodedInstructionResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let data = decode_instruction_data(&req.data)?;
+    let instruction = unpack_system_instruction(&data).ok_or((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse::new("Data is not a valid system instruction")),
+        ))?;
+
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: describe_system_instruction(instruction),
+    }))
+}
+
+fn decode_batch_entry(instruction: &SerializableInstruction) -> DecodedBatchEntry {
+    let mut entry = DecodedBatchEntry {
+        program_id: instruction.program_id.clone(),
+        program: "unknown",
+        instruction: None,
+        fields: None,
+        raw_data: None,
+        error: None,
     };
-    let mint_pubkey = match Pubkey::from_str(&req.mint) {
-        Ok(pk) => pk,
-        Err(_) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid mint public key")))),
+    let Ok(data) = general_purpose::STANDARD.decode(&instruction.instruction_data) else {
+        entry.error = Some("Invalid instruction data; must be base64".to_string());
+        return entry;
     };
-    let owner_pubkey = match Pubkey::from_str(&req.owner) {
-        Ok(pk) => pk,
-        Err(_) => return Err((StatusCode::BAD_REQUEST, Json(ErrorResponse::new("Invalid owner public key")))),
+    let decoded = match Pubkey::from_str(&instruction.program_id) {
+        Ok(program_id) if program_id == solana_system_interface::program::ID => {
+            entry.program = "system";
+            Some(
+                unpack_system_instruction(&data)
+                    .map(describe_system_instruction)
+                    .ok_or("Data is not a valid system instruction"),
+            )
+        }
+        Ok(program_id) if program_id == spl_token::ID || program_id == spl_token_2022::ID => {
+            entry.program = if program_id == spl_token::ID { "token" } else { "token2022" };
+            Some(
+                TokenInstruction::unpack(&data)
+                    .map(describe_token_instruction)
+                    .map_err(|_| "Data is not a valid SPL token instruction"),
+            )
+        }
+        Ok(_) => None,
+        Err(_) => Some(Err("Invalid instruction program id")),
     };
+    match decoded {
+        Some(Ok(decoded)) => {
+            entry.instruction = Some(decoded.instruction);
+            entry.fields = Some(decoded.fields);
+        }
+        Some(Err(error)) => {
+            entry.raw_data = Some(hex::encode(&data));
+            entry.error = Some(error.to_string());
+        }
+        None => entry.raw_data = Some(hex::encode(&data)),
+    }
+    entry
+}
 
-    let source_token_account = spl_associated_token_instruction::get_associated_token_address(&owner_pubkey, &mint_pubkey);
+/// Decodes each instruction independently; a failing entry is marked with an
+/// error instead of failing the whole batch.
+async fn decode_instruction_batch(
+    State(state): State<AppState>,
+    Json(req): Json<DecodeBatchRequest>,
+) -> Result<Json<SuccessResponse<DecodeBatchResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let mut validation = Validation::default();
+    if req.instructions.is_empty() {
+        validation.fail("instructions", "must not be empty", "At least one instruction is required");
+    }
+    validation.batch_limit("instructions", req.instructions.len(), state.batch_limits.decode, "instructions");
+    validation.finish()?;
 
-    match spl_token::instruction::transfer(
-        &spl_token::ID,
-        &source_token_account,
-        &destination_pubkey,
-        &owner_pubkey,
-        &[],
-        req.amount,
-    ) {
-        Ok(instruction) => {
-            let serializable_instruction: SerializableInstruction = instruction.into();
-            Ok(Json(SuccessResponse {
-                success: true,
-                data: serializable_instruction,
-            }))
+    Ok(Json(SuccessResponse {
+        success: true,
+        data: DecodeBatchResponse {
+            results: req.instructions.iter().map(decode_batch_entry).collect(),
+        },
+    }))
+}
+
+async fn not_found() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse::new("Not found").with_code("NOT_FOUND")),
+    )
+}
+
+async fn method_not_allowed() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::METHOD_NOT_ALLOWED,
+        Json(ErrorResponse::new("Method not allowed").with_code("METHOD_NOT_ALLOWED")),
+    )
+}
+
+/// Connection-level settings for `serve`, read from the environment at startup.
+struct ServeOptions {
+    /// `ENABLE_HTTP2`: also accept cleartext HTTP/2 (h2c with prior knowledge).
+    http2: bool,
+    /// `TCP_KEEPALIVE_SECS`: idle time before probing, also used as the probe interval.
+    tcp_keepalive: Option<Duration>,
+    /// `MAX_CONNECTIONS`: connections served at once; further clients wait in
+    /// the listen backlog until a slot frees up.
+    max_connections: Option<usize>,
+}
+
+/// Accepts connections and serves `app` on each. HTTP/1.1 is always available;
+/// with `http2` the same port also accepts h2c, negotiated per connection by
+/// the client preface.
+async fn serve(listener: TcpListener, app: Router, options: ServeOptions) {
+    let limiter = options.max_connections.map(|max| Arc::new(Semaphore::new(max)));
+    let keepalive = options
+        .tcp_keepalive
+        .map(|idle| socket2::TcpKeepalive::new().with_time(idle).with_interval(idle));
+
+    loop {
+        // Take a slot before accepting so the limit is enforced at the socket.
+        let permit = match &limiter {
+            Some(limiter) => Some(match limiter.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => {
+                    tracing::warn!(
+                        max = options.max_connections,
+                        "connection limit reached; waiting for a connection to close"
+                    );
+                    limiter.clone().acquire_owned().await.expect("semaphore is never closed")
+                }
+            }),
+            None => None,
+        };
+
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to accept connection");
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                continue;
+            }
+        };
+        if let Some(keepalive) = &keepalive
+            && let Err(e) = socket2::SockRef::from(&stream).set_tcp_keepalive(keepalive)
+        {
+            tracing::warn!(%peer, error = %e, "failed to set TCP keep-alive");
         }
-        Err(e) => Err((
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse::new(&format!("Failed to create instruction: {}", e))),
-        )),
+
+        let service = TowerToHyperService::new(app.clone());
+        let http2 = options.http2;
+        tokio::spawn(async move {
+            let _permit = permit;
+            let mut builder = auto::Builder::new(TokioExecutor::new());
+            if !http2 {
+                builder = builder.http1_only();
+            }
+            if let Err(e) = builder.serve_connection(TokioIo::new(stream), service).await {
+                tracing::debug!(%peer, error = %e, "connection closed with error");
+            }
+        });
     }
 }
 
 #[tokio::main]
 async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+        )
+        .init();
 
-    let app = Router::new()
+    let commitment = match std::env::var("RPC_COMMITMENT") {
+        Ok(value) => parse_commitment(&value).expect("RPC_COMMITMENT must be processed, confirmed or finalized"),
+        Err(_) => CommitmentConfig::confirmed(),
+    };
+    let rpc_url = std::env::var("RPC_URL").ok();
+    let max_retries = match std::env::var("RPC_MAX_RETRIES") {
+        Ok(value) => value.parse().expect("RPC_MAX_RETRIES must be a number"),
+        Err(_) => 3,
+    };
+    let backoff = match std::env::var("RPC_BACKOFF_MS") {
+        Ok(value) => Duration::from_millis(value.parse().expect("RPC_BACKOFF_MS must be a number of milliseconds")),
+        Err(_) => Duration::from_millis(200),
+    };
+    let rpc = rpc_url.clone().map(|url| {
+        let sender = RetrySender { inner: HttpSender::new(url), max_retries, backoff };
+        Arc::new(RpcClient::new_sender(sender, RpcClientConfig::with_commitment(commitment)))
+    });
+    let server_signing = env_flag("ENABLE_SERVER_SIGNING");
+    if server_signing {
+        tracing::warn!("server-side signing is enabled; clients may send secret keys to /transaction/sign");
+    }
+    let test_mode = env_flag("TEST_MODE");
+    if test_mode {
+        tracing::warn!("test mode is enabled; /keypair accepts caller-supplied entropy");
+    }
+    let read_only = env_flag("READ_ONLY");
+    if read_only {
+        tracing::warn!("read-only mode is enabled; signing endpoints are disabled");
+    }
+    let cluster_info_ttl = match std::env::var("CLUSTER_INFO_CACHE_MS") {
+        Ok(value) => Duration::from_millis(
+            value.parse().expect("CLUSTER_INFO_CACHE_MS must be a number of milliseconds"),
+        ),
+        Err(_) => Duration::from_millis(400),
+    };
+    let cache_ttl = |name: &str, default_ms: u64| match std::env::var(name) {
+        Ok(value) => Duration::from_millis(
+            value.parse().unwrap_or_else(|_| panic!("{} must be a number of milliseconds", name)),
+        ),
+        Err(_) => Duration::from_millis(default_ms),
+    };
+    let mint_cache_ttl = cache_ttl("MINT_CACHE_TTL_MS", 60_000);
+    let account_cache_ttl = cache_ttl("ACCOUNT_CACHE_TTL_MS", 2_000);
+    let info_cache_size: usize = match std::env::var("INFO_CACHE_SIZE") {
+        Ok(value) => value.parse().expect("INFO_CACHE_SIZE must be a number of entries"),
+        Err(_) => 1024,
+    };
+    let default_decimals = match std::env::var("DEFAULT_TOKEN_DECIMALS") {
+        Ok(value) => value.parse().expect("DEFAULT_TOKEN_DECIMALS must be a number"),
+        Err(_) => 9,
+    };
+    assert!(default_decimals <= MAX_TOKEN_DECIMALS, "DEFAULT_TOKEN_DECIMALS must be at most {}", MAX_TOKEN_DECIMALS);
+    let scrypt_concurrency = match std::env::var("SCRYPT_CONCURRENCY") {
+        Ok(value) => value
+            .parse()
+            .ok()
+            .filter(|max| *max > 0)
+            .expect("SCRYPT_CONCURRENCY must be a positive integer"),
+        Err(_) => DEFAULT_SCRYPT_CONCURRENCY,
+    };
+    let vanity_concurrency = match std::env::var("VANITY_CONCURRENCY") {
+        Ok(value) => value
+            .parse()
+            .ok()
+            .filter(|max| *max > 0)
+            .expect("VANITY_CONCURRENCY must be a positive integer"),
+        Err(_) => DEFAULT_VANITY_CONCURRENCY,
+    };
+    let request_timeout = std::env::var("REQUEST_TIMEOUT_MS").ok().map(|value| {
+        Duration::from_millis(value.parse().expect("REQUEST_TIMEOUT_MS must be a number of milliseconds"))
+    });
+    let state = AppState {
+        rpc,
+        server_signing,
+        read_only,
+        cluster_info_ttl,
+        cluster_info_cache: Arc::new(Mutex::new(None)),
+        mint_cache: Arc::new(AccountCache::new(mint_cache_ttl, info_cache_size)),
+        account_cache: Arc::new(AccountCache::new(account_cache_ttl, info_cache_size)),
+        stats: Arc::new(RequestStats::new()),
+        request_timeout,
+        hmac_secret: std::env::var("API_HMAC_SECRET").ok().map(|secret| Arc::new(secret.into_bytes())),
+        hmac_max_skew: match std::env::var("API_HMAC_MAX_SKEW_SECS") {
+            Ok(value) => Duration::from_secs(value.parse().expect("API_HMAC_MAX_SKEW_SECS must be a number of seconds")),
+            Err(_) => Duration::from_secs(300),
+        },
+        default_decimals,
+        test_mode,
+        batch_limits: BatchLimits::from_env(),
+        scrypt_permits: Arc::new(Semaphore::new(scrypt_concurrency)),
+        vanity_permits: Arc::new(Semaphore::new(vanity_concurrency)),
+    };
+
+    let hmac_enabled = state.hmac_secret.is_some();
+    let batch_limits = state.batch_limits;
+
+    let routes = Router::new()
+        .route("/health", get(health))
+        .route("/stats", get(stats))
+        .route("/cluster/info", get(cluster_info))
         .route("/keypair", post(generate_keypair))
+        .route("/keypair/with-create-account", post(generate_keypair_with_create_account))
+        .route("/keypair/inspect", post(inspect_keypair))
+        .route("/keypair/pubkey", post(keypair_pubkey))
+        .route("/keypair/detect-format", post(detect_key_format))
+        .route("/keypair/sign-challenge", post(sign_challenge))
+        .route("/keypair/off-curve", post(generate_off_curve_address))
+        .route("/keypair/hd-batch", post(generate_hd_batch))
+        .route("/keypair/vanity", post(generate_vanity_keypair))
+        .route("/keypair/encrypted", post(generate_encrypted_keypair))
+        .route("/keypair/decrypt", post(decrypt_keypair))
+        .route("/derivation/validate", post(validate_derivation_path))
+        .route("/account/classify", post(classify_account))
+        .route("/account/balances", post(sol_balances))
+        .route("/pay/url", post(pay_url))
+        .route("/pay/verify", post(pay_verify))
         .nest("/token", Router::new()
             .route("/create", post(create_token))
-            .route("/mint", post(mint_token)))
+            .route("/launch", post(launch_token))
+            .route("/mint", post(mint_token))
+            .route("/mint-batch", post(mint_batch))
+            .route("/disable-mint-authority", post(disable_mint_authority))
+            .route("/wrap-sol", post(wrap_sol))
+            .route("/unwrap-sol", post(unwrap_sol))
+            .route("/create-native-account", post(create_native_account))
+            .route("/close-many", post(close_many))
+            .route("/gated-transfer", post(gated_transfer))
+            .route("/derive-addresses", post(derive_addresses))
+            .route("/verify-ata", post(verify_ata))
+            .route("/ata-status", post(ata_status))
+            .route("/balances", post(token_balances))
+            .route("/initialize-account-2022", post(initialize_account_2022))
+            .route("/update-metadata", post(update_token_metadata))
+            .route("/account-info", post(token_account_info))
+            .route("/mint-info", post(mint_info))
+            .route("/supply", post(token_supply)))
         .nest("/message", Router::new()
             .route("/sign", post(sign_message))
-            .route("/verify", post(verify_message)))
+            .route("/sign-offchain", post(sign_message_offchain))
+            .route("/sign-permit", post(sign_permit))
+            .route("/hash", post(hash_message))
+            .route("/compile", post(compile_message))
+            .route("/verify", post(verify_message))
+            .route("/verify-any", post(verify_message_any))
+            .route("/verify-permit", post(verify_permit))
+            .route("/verify-batch-fast", post(verify_message_batch_fast)))
+        .route("/signature/convert", post(convert_signature))
+        .route("/estimate/funding", post(estimate_funding))
+        .route("/rent/by-type", post(rent_by_type))
+        .route("/system/allocate-assign-with-seed", post(allocate_assign_with_seed))
+        .route("/system/create-account-with-seed", post(create_account_with_seed))
         .nest("/send", Router::new()
             .route("/sol", post(send_sol))
-            .route("/token", post(send_token)));
+            .route("/sol-preflight", post(send_sol_preflight))
+            .route("/token", post(send_token))
+            .route("/token-split", post(send_token_split)))
+        .nest("/nonce", Router::new()
+            .route("/create-full", post(create_nonce_account))
+            .route("/authorize", post(authorize_nonce_account)))
+        .route("/instructions/build", post(build_instructions))
+        .route("/operations", get(list_operations))
+        .nest("/instruction", Router::new()
+            .route("/decode-token", post(decode_token_instruction))
+            .route("/decode-system", post(decode_system_instruction))
+            .route("/decode-batch", post(decode_instruction_batch))
+            .route("/rebuild", post(rebuild_instruction))
+            .route("/custom", post(custom_instruction)))
+        .nest("/transaction", Router::new()
+            .route("/build", post(build_transaction))
+            .route("/estimate-fee", post(estimate_fee))
+            .route("/size", post(transaction_size))
+            .route("/check-policy", post(check_transaction_policy))
+            .route("/summary", post(transaction_summary))
+            .route("/build-with-nonce", post(build_transaction_with_nonce))
+            .route("/status", post(transaction_status))
+            .route("/get", post(transaction_get))
+            .merge(Router::new()
+                .route("/sign", post(sign_transaction))
+                .route("/sign-partial", post(sign_transaction_partial))
+                .route_layer(middleware::from_fn_with_state(state.clone(), require_hmac))))
+        .route_layer(middleware::from_fn_with_state(state.clone(), count_route));
 
-    let listener = TcpListener::bind("0.0.0.0:8080").await.unwrap();
-    axum32::serve(listener, app).await.unwrap();
-}
\ No newline at end of file
+    // `ROUTE_PREFIX` nests everything (e.g. under `/api/v1`); `HEALTH_AT_ROOT`
+    // additionally keeps `/health` and `/stats` reachable without it.
+    let route_prefix = std::env::var("ROUTE_PREFIX")
+        .map(|prefix| format!("/{}", prefix.trim_matches('/')))
+        .ok()
+        .filter(|prefix| prefix != "/");
+    let app = match route_prefix.as_deref() {
+        None => routes,
+        Some(prefix) => {
+            let app = Router::new().nest(prefix, routes);
+            if env_flag("HEALTH_AT_ROOT") {
+                app.merge(
+                    Router::new()
+                        .route("/health", get(health))
+                        .route("/stats", get(stats))
+                        .route_layer(middleware::from_fn_with_state(state.clone(), count_route)),
+                )
+            } else {
+                app
+            }
+        }
+    };
+    let app = app
+        .fallback(not_found)
+        .method_not_allowed_fallback(method_not_allowed)
+        .layer(middleware::from_fn_with_state(state.clone(), limit_json_arrays))
+        .layer(middleware::from_fn_with_state(state.clone(), enforce_deadline))
+        .layer(middleware::from_fn_with_state(state.clone(), count_request))
+        .layer(middleware::from_fn(errors_as_200))
+        .layer(middleware::from_fn(pretty_json))
+        .layer(middleware::from_fn(msgpack_response))
+        .layer(middleware::from_fn(correlation_id))
+        .layer(
+            CompressionLayer::new()
+                .compress_when(DefaultPredicate::new().and(SizeAbove::new(COMPRESSION_MIN_BYTES))),
+        )
+        .with_state(state);
+
+    let bind_addr = "0.0.0.0:8080";
+    let listener = TcpListener::bind(bind_addr).await.unwrap();
+    let options = ServeOptions {
+        http2: env_flag("ENABLE_HTTP2"),
+        tcp_keepalive: std::env::var("TCP_KEEPALIVE_SECS").ok().map(|value| {
+            Duration::from_secs(value.parse().expect("TCP_KEEPALIVE_SECS must be a number of seconds"))
+        }),
+        max_connections: std::env::var("MAX_CONNECTIONS").ok().map(|value| {
+            value
+                .parse()
+                .ok()
+                .filter(|max| *max > 0)
+                .expect("MAX_CONNECTIONS must be a positive integer")
+        }),
+    };
+    tracing::info!(
+        bind = bind_addr,
+        route_prefix = route_prefix.as_deref().unwrap_or(""),
+        rpc_url = rpc_url.as_deref().map(redact_url).as_deref().unwrap_or("unset"),
+        commitment = ?commitment.commitment,
+        rpc_max_retries = max_retries,
+        rpc_backoff_ms = backoff.as_millis() as u64,
+        server_signing,
+        read_only,
+        test_mode,
+        hmac_auth = hmac_enabled,
+        http2 = options.http2,
+        default_decimals,
+        request_timeout_ms = request_timeout.map(|timeout| timeout.as_millis() as u64),
+        cluster_info_cache_ms = cluster_info_ttl.as_millis() as u64,
+        mint_cache_ttl_ms = mint_cache_ttl.as_millis() as u64,
+        account_cache_ttl_ms = account_cache_ttl.as_millis() as u64,
+        info_cache_size,
+        max_batch_keypairs = batch_limits.keypairs.max,
+        max_batch_transfers = batch_limits.transfers.max,
+        max_batch_mints = batch_limits.mints.max,
+        max_batch_accounts = batch_limits.accounts.max,
+        max_batch_decode = batch_limits.decode.max,
+        max_batch_signatures = batch_limits.signatures.max,
+        max_batch_close_accounts = batch_limits.close_accounts.max,
+        max_batch_funding_operations = batch_limits.funding_operations.max,
+        scrypt_concurrency,
+        vanity_concurrency,
+        tcp_keepalive_secs = options.tcp_keepalive.map(|keepalive| keepalive.as_secs()),
+        max_connections = options.max_connections,
+        "effective configuration"
+    );
+    serve(listener, app, options).await;
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What `unsigned_integer_problem` makes of the error serde_json raises
+    /// for `json` as a `T`, or `None` when it parses.
+    fn problem<T: DeserializeOwned>(json: &str) -> Option<String> {
+        let error = serde_json::from_str::<T>(json).err()?;
+        unsigned_integer_problem(&error.to_string())
+    }
+
+    #[test]
+    fn negative_integer_must_be_non_negative() {
+        assert_eq!(problem::<u64>("-1").as_deref(), Some("must be a non-negative integer"));
+        assert_eq!(problem::<u8>("-300").as_deref(), Some("must be a non-negative integer"));
+    }
+
+    #[test]
+    fn fraction_must_be_an_integer() {
+        assert_eq!(problem::<u32>("1.5").as_deref(), Some("must be a non-negative integer"));
+        assert_eq!(problem::<u64>("-0.5").as_deref(), Some("must be a non-negative integer"));
+    }
+
+    #[test]
+    fn overflow_reports_the_type_maximum() {
+        assert_eq!(problem::<u8>("256").as_deref(), Some("must be at most 255"));
+        assert_eq!(problem::<u16>("70000").as_deref(), Some("must be at most 65535"));
+        assert_eq!(problem::<u32>("4294967296").as_deref(), Some("must be at most 4294967295"));
+        assert_eq!(problem::<u64>("18446744073709551616").as_deref(), Some("must be at most 18446744073709551615"));
+        assert_eq!(problem::<u64>("1e20").as_deref(), Some("must be at most 18446744073709551615"));
+    }
+
+    #[test]
+    fn valid_values_and_other_errors_are_left_alone() {
+        assert_eq!(problem::<u8>("255"), None);
+        assert_eq!(problem::<u64>("0"), None);
+        assert_eq!(problem::<u64>("\"12\""), None);
+        assert_eq!(problem::<i64>("-1"), None);
+    }
+}